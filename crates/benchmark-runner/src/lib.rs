@@ -1,35 +1,480 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    process::Command,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use alloy_genesis::Genesis;
 use rayon::prelude::*;
-use witness_generator::{generate_stateless_witness, BlocksAndWitnesses};
-use zkevm_metrics::WorkloadMetrics;
+use witness_generator::{generate_stateless_witness, rlp_chain, rpc_dump, ClientInput};
+use zkevm_metrics::{
+    results_name, BlockMetrics, BlockProfile, OperationProfile, RejectionMetrics, RunManifest,
+    TransactionMetrics, WorkloadMetrics, WorkloadStatus, RUN_MANIFEST_FILE_NAME, SCHEMA_VERSION,
+};
+
+pub use zkevm_metrics::ExecutionReportMetrics;
 
-pub fn run_benchmark<F>(elf_path: &'static [u8], metrics_path_prefix: &str, zkvm_executor: F)
+pub use zkevm_guest::{
+    profiling::{OPCODE_REGION_PREFIX, PRECOMPILE_REGION_PREFIX},
+    region::REGION_REPORT_PREFIX,
+    validation::TRANSACTION_REGION_PREFIX,
+    BlockOutput, BlockPublicValues, ExecutionCounts, GuestOutput, MAX_REJECTION_LEN,
+};
+
+/// Outcome of executing a single block inside a zkVM.
+#[derive(Debug)]
+pub struct BlockExecution {
+    /// Total number of cycles for the whole guest execution.
+    pub total_num_cycles: u64,
+    /// Cycles spent in each named region of the guest program.
+    ///
+    /// Region names are the ones passed to `zkevm_guest::Region`, whatever the zkVM.
+    pub region_cycles: HashMap<String, u64>,
+    /// Whether the zkVM does not report cycle counts, leaving `total_num_cycles` and
+    /// `region_cycles` empty rather than measured.
+    pub cycles_unsupported: bool,
+    /// Output committed by the guest.
+    pub output: GuestOutput,
+    /// Details of the execution beyond cycle counts, if the zkVM reports them.
+    pub execution_report: Option<ExecutionReportMetrics>,
+}
+
+/// Executes every block of every generated corpus with `zkvm_executor` and writes the
+/// resulting metrics to `zkevm-metrics/<metrics_path_prefix>/<corpus>.json`.
+///
+/// The public values of each execution are checked against the block that was fed to the
+/// guest; a mismatch, or the rejection of a valid block, is recorded as a correctness failure
+/// on the block's metrics. Blocks that the corpus expects to be invalid instead record whether
+/// the guest rejected them, and accepting one is a correctness failure as well.
+///
+/// If the guest was built with the `profiling` feature, the opcode and precompile profiles of
+/// the valid blocks are written to `zkevm-metrics/<metrics_path_prefix>/<corpus>.profile.json`.
+///
+/// Once every corpus has run, a `RunManifest` describing the run is written to
+/// `zkevm-metrics/<metrics_path_prefix>/run.manifest.json`.
+///
+/// When xtask builds the guest with part of the zkVM's patch-set, it names the variant in
+/// `ZKEVM_RUN_VARIANT`, and the results go to `zkevm-metrics/<metrics_path_prefix>-<variant>/`
+/// instead. The patches the guest was built with, listed in `ZKEVM_PATCHES`, are recorded in
+/// the manifest.
+///
+/// Blocks recorded from a real chain are benchmarked as well when `ZKEVM_RPC_DUMPS` names a
+/// directory of RPC dumps, relative to the workspace root, each becoming a corpus (see
+/// `witness_generator::rpc_dump`). Likewise, `ZKEVM_RLP_CHAINS` names a directory of chains of
+/// RLP-encoded blocks, executed to generate their witnesses (see `witness_generator::rlp_chain`).
+pub fn run_benchmark<F>(metrics_path_prefix: &str, zkvm_executor: F)
 where
-    F: Fn(&BlocksAndWitnesses, &'static [u8]) -> Vec<WorkloadMetrics> + Send + Sync,
+    F: Fn(&ClientInput, &Genesis) -> BlockExecution + Send + Sync,
 {
-    let generated_corpuses = generate_stateless_witness::generate();
+    let started_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let start = Instant::now();
+    let variant = std::env::var("ZKEVM_RUN_VARIANT")
+        .ok()
+        .filter(|variant| !variant.is_empty());
+    let patches = std::env::var("ZKEVM_PATCHES").ok().map(|patches| {
+        patches
+            .split(',')
+            .filter(|key| !key.is_empty())
+            .map(str::to_string)
+            .collect()
+    });
+    let metrics_dir = format!(
+        "{}/{}/{}",
+        env!("CARGO_WORKSPACE_DIR"),
+        "zkevm-metrics",
+        results_name(metrics_path_prefix, variant.as_deref())
+    );
+
+    let mut generated_corpuses = generate_stateless_witness::generate();
+    if let Some(dumps) = std::env::var_os("ZKEVM_RPC_DUMPS") {
+        let dumps = std::path::Path::new(env!("CARGO_WORKSPACE_DIR")).join(dumps);
+        let imported = rpc_dump::import_all(&dumps).unwrap_or_else(|err| {
+            panic!("failed to import RPC dumps from {}: {err}", dumps.display())
+        });
+        generated_corpuses.extend(imported);
+    }
+    if let Some(chains) = std::env::var_os("ZKEVM_RLP_CHAINS") {
+        let chains = std::path::Path::new(env!("CARGO_WORKSPACE_DIR")).join(chains);
+        let generated = rlp_chain::generate_all(&chains).unwrap_or_else(|err| {
+            panic!(
+                "failed to generate witnesses for the chains in {}: {err}",
+                chains.display()
+            )
+        });
+        generated_corpuses.extend(generated);
+    }
+    let corpora: Vec<String> = generated_corpuses
+        .iter()
+        .map(|bw| bw.name.clone())
+        .collect();
+    let blocks = generated_corpuses
+        .iter()
+        .map(|bw| bw.blocks_and_witnesses.len())
+        .sum();
 
     generated_corpuses.into_par_iter().for_each(|bw| {
         println!("{} (num_blocks={})", bw.name, bw.blocks_and_witnesses.len());
 
-        let reports = zkvm_executor(&bw, elf_path);
-
-        WorkloadMetrics::to_path(
-            format!(
-                "{}/{}/{}/{}.json",
-                env!("CARGO_WORKSPACE_DIR"),
-                "zkevm-metrics",
-                metrics_path_prefix,
-                bw.name
-            ),
-            &reports,
-        )
-        .unwrap();
+        let num_blocks = bw.blocks_and_witnesses.len();
+        let (reports, profiles): (Vec<_>, Vec<_>) = bw
+            .blocks_and_witnesses
+            .iter()
+            .enumerate()
+            .map(|(index, client_input)| {
+                let name = format!("{}-{}", bw.name, client_input.block.number);
+                let mut execution = zkvm_executor(client_input, &bw.network);
+
+                // Only the last block of a corpus can be expected to be invalid.
+                let expected_exception = bw
+                    .expected_exception
+                    .as_ref()
+                    .filter(|_| index + 1 == num_blocks);
+
+                let tx_gas_used = execution
+                    .output
+                    .as_ref()
+                    .map(|output| output.tx_gas_used.as_slice())
+                    .unwrap_or_default();
+                let transactions =
+                    take_transaction_metrics(&mut execution.region_cycles, tx_gas_used);
+                let counts = execution
+                    .output
+                    .as_ref()
+                    .ok()
+                    .and_then(|output| output.profile.as_ref());
+                let profile =
+                    take_block_profile(name.clone(), &mut execution.region_cycles, counts);
+
+                let (error, rejection) = match (expected_exception, execution.output) {
+                    (None, Ok(output)) => (
+                        check_public_values(client_input, &output.public_values),
+                        None,
+                    ),
+                    (None, Err(error)) => {
+                        (Some(format!("valid block was rejected: {error}")), None)
+                    }
+                    (Some(expected_exception), output) => {
+                        let rejection = RejectionMetrics {
+                            expected_exception: expected_exception.clone(),
+                            matched: output.is_err(),
+                            error: output.err(),
+                        };
+                        let error = (!rejection.matched).then(|| {
+                            format!(
+                                "expected rejection with {}, but the block was accepted",
+                                rejection.expected_exception
+                            )
+                        });
+                        (error, Some(rejection))
+                    }
+                };
+                let status = match &error {
+                    Some(error) => {
+                        eprintln!("{name}: correctness failure: {error}");
+                        WorkloadStatus::CorrectnessFailure
+                    }
+                    None => WorkloadStatus::Success,
+                };
+
+                let metrics = WorkloadMetrics {
+                    schema_version: SCHEMA_VERSION,
+                    name,
+                    total_num_cycles: execution.total_num_cycles,
+                    region_cycles: execution.region_cycles.into(),
+                    cycles_unsupported: execution.cycles_unsupported,
+                    status,
+                    error,
+                    rejection,
+                    transactions,
+                    block: Some(block_metrics(client_input)),
+                    execution_report: execution.execution_report,
+                };
+                (metrics, profile)
+            })
+            .unzip();
+
+        WorkloadMetrics::to_path(format!("{metrics_dir}/{}.json", bw.name), &reports).unwrap();
+
+        let profiles: Vec<_> = profiles.into_iter().flatten().collect();
+        if !profiles.is_empty() {
+            BlockProfile::to_path(format!("{metrics_dir}/{}.profile.json", bw.name), &profiles)
+                .unwrap();
+        }
 
         println!(
             "Finished processing and saved metrics for corpus: {}. Number of reports: {}",
             bw.name,
             reports.len()
         );
-        // dbg!(&reports);
     });
+
+    let manifest = RunManifest {
+        zkvm: metrics_path_prefix.to_string(),
+        variant,
+        patches,
+        started_at,
+        duration_secs: start.elapsed().as_secs(),
+        git_commit: git_commit(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        cpus: rayon::current_num_threads(),
+        corpora,
+        blocks,
+    };
+    manifest
+        .to_path(format!("{metrics_dir}/{RUN_MANIFEST_FILE_NAME}"))
+        .unwrap();
+}
+
+/// Returns the commit checked out in the workspace, or `None` if git is unavailable.
+fn git_commit() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(env!("CARGO_WORKSPACE_DIR"))
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Records the gas used, transaction count and witness size of the block in `input`.
+pub fn block_metrics(input: &ClientInput) -> BlockMetrics {
+    let witness = &input.witness;
+    let witness_size = [
+        &witness.state,
+        &witness.codes,
+        &witness.keys,
+        &witness.headers,
+    ]
+    .into_iter()
+    .flatten()
+    .map(|bytes| bytes.len())
+    .sum();
+
+    BlockMetrics {
+        gas_used: input.block.gas_used,
+        tx_count: input.block.body.transactions.len(),
+        witness_size,
+    }
+}
+
+/// Compares the public values committed by the guest with the block in `input`.
+///
+/// Returns a description of every mismatching value, or `None` if they all match.
+pub fn check_public_values(input: &ClientInput, committed: &BlockPublicValues) -> Option<String> {
+    let header = &input.block.header;
+    let (block_hash, parent_hash, state_root) = *committed;
+
+    let mismatches: Vec<_> = [
+        ("block hash", block_hash, header.hash_slow()),
+        ("parent hash", parent_hash, header.parent_hash),
+        ("state root", state_root, header.state_root),
+    ]
+    .into_iter()
+    .filter(|(_, got, expected)| got != expected)
+    .map(|(what, got, expected)| format!("{what}: expected {expected}, got {got}"))
+    .collect();
+
+    (!mismatches.is_empty()).then(|| mismatches.join("; "))
+}
+
+/// Removes the per-transaction regions from `region_cycles` and pairs their cycles with the
+/// gas used by each transaction, as committed by the guest.
+///
+/// Transactions without a region (e.g., on zkVMs that do not report region cycles) are
+/// reported with zero cycles. Without gas data, as for rejected blocks, no transaction is
+/// reported.
+pub fn take_transaction_metrics(
+    region_cycles: &mut HashMap<String, u64>,
+    tx_gas_used: &[u64],
+) -> Vec<TransactionMetrics> {
+    let mut tx_cycles: HashMap<usize, u64> = HashMap::new();
+    region_cycles.retain(|name, cycles| {
+        match name
+            .strip_prefix(TRANSACTION_REGION_PREFIX)
+            .and_then(|index| index.parse().ok())
+        {
+            Some(index) => {
+                tx_cycles.insert(index, *cycles);
+                false
+            }
+            None => true,
+        }
+    });
+
+    tx_gas_used
+        .iter()
+        .enumerate()
+        .map(|(index, &gas_used)| TransactionMetrics {
+            index,
+            gas_used,
+            cycles: tx_cycles.get(&index).copied().unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Removes the opcode and precompile regions of a profiling build from `region_cycles` and
+/// pairs their cycles with the execution `counts` committed by the guest.
+///
+/// Returns `None` if the guest committed no counts, i.e., it is not a profiling build or it
+/// rejected the block. The regions are removed in any case, so that they never end up in the
+/// block's `WorkloadMetrics`.
+pub fn take_block_profile(
+    name: String,
+    region_cycles: &mut HashMap<String, u64>,
+    counts: Option<&ExecutionCounts>,
+) -> Option<BlockProfile> {
+    let counts_committed = counts.is_some();
+    let counts = counts.cloned().unwrap_or_default();
+    let mut opcodes: BTreeMap<String, OperationProfile> = counts
+        .opcodes
+        .iter()
+        .map(|(opcode, &count)| (opcode.clone(), OperationProfile { count, cycles: 0 }))
+        .collect();
+    let mut precompiles: BTreeMap<String, OperationProfile> = counts
+        .precompiles
+        .iter()
+        .map(|(address, &count)| (address.to_string(), OperationProfile { count, cycles: 0 }))
+        .collect();
+
+    region_cycles.retain(|region, &mut cycles| {
+        let (profiles, key) = if let Some(opcode) = region.strip_prefix(OPCODE_REGION_PREFIX) {
+            (&mut opcodes, opcode)
+        } else if let Some(address) = region.strip_prefix(PRECOMPILE_REGION_PREFIX) {
+            (&mut precompiles, address)
+        } else {
+            return true;
+        };
+        profiles.entry(key.to_string()).or_default().cycles += cycles;
+        false
+    });
+
+    counts_committed.then_some(BlockProfile {
+        name,
+        opcodes,
+        precompiles,
+    })
+}
+
+/// Collects the region cycles reported by a guest running on a platform without a native
+/// cycle tracker, from the guest's captured `output`.
+///
+/// Only lines starting with [`REGION_REPORT_PREFIX`] are considered. Reports for the same
+/// region are summed, matching the behaviour of the native cycle trackers.
+pub fn parse_region_reports(output: &str) -> HashMap<String, u64> {
+    let mut region_cycles = HashMap::new();
+    for report in output
+        .lines()
+        .filter_map(|line| line.strip_prefix(REGION_REPORT_PREFIX))
+    {
+        let (name, cycles) = report
+            .rsplit_once(' ')
+            .and_then(|(name, cycles)| Some((name, cycles.parse::<u64>().ok()?)))
+            .unwrap_or_else(|| panic!("malformed region report: {report}"));
+        *region_cycles.entry(name.to_string()).or_default() += cycles;
+    }
+    region_cycles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn region_reports_are_summed_per_name() {
+        let output = format!(
+            "some guest output\n\
+             {REGION_REPORT_PREFIX}read_input 10\n\
+             {REGION_REPORT_PREFIX}validation/execution 5\n\
+             {REGION_REPORT_PREFIX}validation/execution 7\n"
+        );
+        let region_cycles = parse_region_reports(&output);
+        assert_eq!(
+            region_cycles,
+            HashMap::from([
+                ("read_input".to_string(), 10),
+                ("validation/execution".to_string(), 12),
+            ])
+        );
+    }
+
+    #[test]
+    fn profile_regions_are_moved_to_block_profile() {
+        let mut region_cycles = HashMap::from([
+            ("validation/execution".to_string(), 100),
+            (format!("{OPCODE_REGION_PREFIX}ADD"), 6),
+            (format!("{OPCODE_REGION_PREFIX}SSTORE"), 40),
+        ]);
+        let counts = ExecutionCounts {
+            opcodes: BTreeMap::from([("ADD".to_string(), 2), ("SSTORE".to_string(), 1)]),
+            precompiles: BTreeMap::new(),
+        };
+
+        let profile = take_block_profile("block".to_string(), &mut region_cycles.clone(), None);
+        assert_eq!(profile, None);
+
+        let profile = take_block_profile("block".to_string(), &mut region_cycles, Some(&counts));
+        assert_eq!(
+            region_cycles,
+            HashMap::from([("validation/execution".to_string(), 100)])
+        );
+        assert_eq!(
+            profile,
+            Some(BlockProfile {
+                name: "block".to_string(),
+                opcodes: BTreeMap::from([
+                    (
+                        "ADD".to_string(),
+                        OperationProfile {
+                            count: 2,
+                            cycles: 6
+                        }
+                    ),
+                    (
+                        "SSTORE".to_string(),
+                        OperationProfile {
+                            count: 1,
+                            cycles: 40
+                        }
+                    ),
+                ]),
+                precompiles: BTreeMap::new(),
+            })
+        );
+    }
+
+    #[test]
+    fn transaction_regions_are_moved_to_transaction_metrics() {
+        let mut region_cycles = HashMap::from([
+            ("validation/execution".to_string(), 100),
+            (format!("{TRANSACTION_REGION_PREFIX}0"), 30),
+            (format!("{TRANSACTION_REGION_PREFIX}1"), 60),
+        ]);
+        let transactions = take_transaction_metrics(&mut region_cycles, &[21_000, 42_000]);
+
+        assert_eq!(
+            region_cycles,
+            HashMap::from([("validation/execution".to_string(), 100)])
+        );
+        assert_eq!(
+            transactions,
+            vec![
+                TransactionMetrics {
+                    index: 0,
+                    gas_used: 21_000,
+                    cycles: 30,
+                },
+                TransactionMetrics {
+                    index: 1,
+                    gas_used: 42_000,
+                    cycles: 60,
+                },
+            ]
+        );
+    }
 }
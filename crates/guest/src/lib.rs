@@ -0,0 +1,98 @@
+#![doc = include_str!("../README.md")]
+
+extern crate alloc;
+
+use alloc::{sync::Arc, vec::Vec};
+
+use alloy_genesis::Genesis;
+use alloy_primitives::B256;
+use reth_chainspec::ChainSpec;
+use reth_stateless::ClientInput;
+use serde_derive::{Deserialize, Serialize};
+
+pub mod platform;
+pub mod profiling;
+pub mod region;
+pub mod validation;
+
+pub use platform::Platform;
+pub use profiling::ExecutionCounts;
+pub use region::{Region, RegionName};
+
+/// Public values committed by every guest program after validating a block:
+/// `(block_hash, parent_hash, state_root)`.
+pub type BlockPublicValues = (B256, B256, B256);
+
+/// Output committed by every guest program for a valid block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockOutput {
+    /// Public values identifying the validated block.
+    pub public_values: BlockPublicValues,
+    /// Gas used by each transaction of the block, in block order.
+    pub tx_gas_used: Vec<u64>,
+    /// Opcode and precompile counts, if the guest was built with the `profiling` feature.
+    pub profile: Option<ExecutionCounts>,
+}
+
+/// Output committed by every guest program: the [`BlockOutput`] if the block is valid,
+/// or the validation error if the guest rejected it.
+///
+/// The guests commit it as a single value, so hosts can decode it in one read.
+pub type GuestOutput = Result<BlockOutput, String>;
+
+/// Longest validation error committed by the guests of zkVMs whose public output is bounded
+/// (see [`Platform::MAX_ERROR_LEN`]), in bytes.
+pub const MAX_REJECTION_LEN: usize = 4096;
+
+/// Runs the stateless validation workload on platform `P`.
+///
+/// Reads a `ClientInput` and the network `Genesis` from the host, validates the block and
+/// commits the [`GuestOutput`].
+pub fn run<P: Platform>() {
+    P::init();
+
+    let (input, genesis) = {
+        let _region = Region::<P>::start("read_input");
+        (P::read::<ClientInput>(), P::read::<Genesis>())
+    };
+
+    let chain_spec: Arc<ChainSpec> = {
+        let _region = Region::<P>::start("chain_spec");
+        Arc::new(genesis.into())
+    };
+
+    let parent_hash = input.block.parent_hash;
+    let state_root = input.block.state_root;
+
+    let output: GuestOutput = {
+        let _region = Region::<P>::start("validation");
+        // Invalid blocks are part of the benchmark, so the validation error is committed
+        // instead of aborting execution.
+        validation::stateless_validation::<P>(input.block, input.witness, chain_spec)
+            .map(|block| BlockOutput {
+                public_values: (block.hash, parent_hash, state_root),
+                tx_gas_used: block.tx_gas_used,
+                profile: block.profile,
+            })
+            .map_err(|err| {
+                let mut err = err.to_string();
+                if let Some(max_len) = P::MAX_ERROR_LEN {
+                    truncate(&mut err, max_len);
+                }
+                err
+            })
+    };
+
+    P::commit(&output);
+}
+
+/// Truncates `text` to at most `max_len` bytes, on a character boundary.
+fn truncate(text: &mut String, max_len: usize) {
+    if text.len() > max_len {
+        let mut len = max_len;
+        while !text.is_char_boundary(len) {
+            len -= 1;
+        }
+        text.truncate(len);
+    }
+}
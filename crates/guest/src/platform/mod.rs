@@ -0,0 +1,72 @@
+//! zkVM platforms the guest workload can run on.
+//!
+//! Each platform lives behind the cargo feature of the same name, so a guest only pulls in
+//! the SDK of the zkVM it is compiled for.
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::region::RegionName;
+
+#[cfg(feature = "openvm")]
+mod openvm;
+#[cfg(feature = "risc0")]
+mod risc0;
+#[cfg(feature = "sp1")]
+mod sp1;
+#[cfg(feature = "zkm")]
+mod zkm;
+
+#[cfg(feature = "openvm")]
+pub use openvm::OpenVm;
+#[cfg(feature = "risc0")]
+pub use risc0::Risc0;
+#[cfg(feature = "sp1")]
+pub use sp1::Sp1;
+#[cfg(feature = "zkm")]
+pub use zkm::Zkm;
+
+/// The zkVM-specific operations the guest workload relies on.
+pub trait Platform {
+    /// Longest validation error the platform can commit, in bytes, if its public output is
+    /// bounded. Longer errors are truncated.
+    const MAX_ERROR_LEN: Option<usize> = None;
+
+    /// Performs any setup the platform needs before the workload starts.
+    fn init() {}
+
+    /// Reads the next value written by the host.
+    fn read<T: DeserializeOwned>() -> T;
+
+    /// Commits `value` as a public output of the execution.
+    fn commit<T: Serialize>(value: &T);
+
+    /// Marks the start of the region `name` for cycle counting.
+    ///
+    /// Use a [`Region`](crate::Region) guard rather than calling this directly.
+    fn region_start(name: RegionName<'_>);
+
+    /// Marks the end of the region `name` for cycle counting.
+    ///
+    /// Regions are strictly nested: this always ends the most recently started region.
+    fn region_end(name: RegionName<'_>);
+}
+
+/// Initializes a basic `tracing` subscriber that mimics `println!` behavior.
+///
+/// This is because we want to use tracing in the `no_std` program to capture cycle counts.
+#[cfg(feature = "tracing")]
+fn init_tracing_just_like_println() {
+    use tracing_subscriber::fmt;
+
+    // Build a formatter that prints *only* the message text + '\n'
+    let plain = fmt::format()
+        .without_time() // no timestamp
+        .with_level(false) // no INFO/TRACE prefix
+        .with_target(false); // no module path
+
+    fmt::Subscriber::builder()
+        .event_format(plain) // use the stripped-down format
+        .with_writer(std::io::stdout) // stdout == println!
+        .with_max_level(tracing::Level::INFO) // capture info! and up
+        .init(); // set as global default
+}
@@ -0,0 +1,39 @@
+use openvm::io::{read, reveal_u32};
+use serde::{Serialize, de::DeserializeOwned};
+
+use super::Platform;
+use crate::{MAX_REJECTION_LEN, region::RegionName};
+
+/// OpenVM.
+///
+/// OpenVM does not support regions: the guest cannot read its cycle count and the SDK does not
+/// report cycles per region, so [`Platform::region_start`] and [`Platform::region_end`] do
+/// nothing rather than spend cycles inside the spans they would measure.
+#[derive(Debug)]
+pub struct OpenVm;
+
+impl Platform for OpenVm {
+    const MAX_ERROR_LEN: Option<usize> = Some(MAX_REJECTION_LEN);
+
+    fn read<T: DeserializeOwned>() -> T {
+        read::<T>()
+    }
+
+    /// OpenVM only exposes fixed-width public values, so the value is bincode-encoded and
+    /// revealed as a little-endian `u32` length prefix followed by the encoded bytes, four
+    /// bytes per word. The host sizes the public values for the largest output a block can
+    /// commit.
+    fn commit<T: Serialize>(value: &T) {
+        let bytes = bincode::serialize(value).unwrap();
+        reveal_u32(bytes.len() as u32, 0);
+        for (index, chunk) in bytes.chunks(4).enumerate() {
+            let mut word = [0u8; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            reveal_u32(u32::from_le_bytes(word), index + 1);
+        }
+    }
+
+    fn region_start(_name: RegionName<'_>) {}
+
+    fn region_end(_name: RegionName<'_>) {}
+}
@@ -0,0 +1,41 @@
+use std::sync::Mutex;
+
+use risc0_zkvm::guest::env;
+use serde::{Serialize, de::DeserializeOwned};
+
+use super::Platform;
+use crate::region::{REGION_REPORT_PREFIX, RegionName};
+
+/// Cycle counts at the start of each region that is currently open.
+static REGION_STARTS: Mutex<Vec<u64>> = Mutex::new(Vec::new());
+
+/// RISC Zero.
+///
+/// RISC Zero has no cycle tracker, so regions are measured in the guest with
+/// `env::cycle_count` and reported on stderr, prefixed with [`REGION_REPORT_PREFIX`].
+#[derive(Debug)]
+pub struct Risc0;
+
+impl Platform for Risc0 {
+    fn read<T: DeserializeOwned>() -> T {
+        env::read::<T>()
+    }
+
+    fn commit<T: Serialize>(value: &T) {
+        env::commit(value);
+    }
+
+    fn region_start(_name: RegionName<'_>) {
+        REGION_STARTS.lock().unwrap().push(env::cycle_count());
+    }
+
+    fn region_end(name: RegionName<'_>) {
+        let end = env::cycle_count();
+        let start = REGION_STARTS
+            .lock()
+            .unwrap()
+            .pop()
+            .expect("region_end called without a matching region_start");
+        eprintln!("{REGION_REPORT_PREFIX}{name} {}", end - start);
+    }
+}
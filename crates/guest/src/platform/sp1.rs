@@ -0,0 +1,32 @@
+use serde::{Serialize, de::DeserializeOwned};
+
+use super::Platform;
+use crate::region::RegionName;
+
+/// Succinct SP1.
+///
+/// Regions are reported through SP1's cycle tracker, which parses markers printed to stdout.
+#[derive(Debug)]
+pub struct Sp1;
+
+impl Platform for Sp1 {
+    fn init() {
+        super::init_tracing_just_like_println();
+    }
+
+    fn read<T: DeserializeOwned>() -> T {
+        sp1_zkvm::io::read::<T>()
+    }
+
+    fn commit<T: Serialize>(value: &T) {
+        sp1_zkvm::io::commit(value);
+    }
+
+    fn region_start(name: RegionName<'_>) {
+        println!("cycle-tracker-report-start: {name}");
+    }
+
+    fn region_end(name: RegionName<'_>) {
+        println!("cycle-tracker-report-end: {name}");
+    }
+}
@@ -0,0 +1,32 @@
+use serde::{Serialize, de::DeserializeOwned};
+
+use super::Platform;
+use crate::region::RegionName;
+
+/// zkMIPS.
+///
+/// Regions are reported through the zkMIPS cycle tracker, which parses markers printed to stdout.
+#[derive(Debug)]
+pub struct Zkm;
+
+impl Platform for Zkm {
+    fn init() {
+        super::init_tracing_just_like_println();
+    }
+
+    fn read<T: DeserializeOwned>() -> T {
+        zkm_zkvm::io::read::<T>()
+    }
+
+    fn commit<T: Serialize>(value: &T) {
+        zkm_zkvm::io::commit(value);
+    }
+
+    fn region_start(name: RegionName<'_>) {
+        println!("cycle-tracker-report-start: {name}");
+    }
+
+    fn region_end(name: RegionName<'_>) {
+        println!("cycle-tracker-report-end: {name}");
+    }
+}
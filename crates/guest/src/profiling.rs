@@ -0,0 +1,142 @@
+//! Per-opcode and per-precompile profiling of the block execution.
+//!
+//! With the `profiling` feature, block execution runs with a [`Profiler`] inspector that tracks
+//! every EVM opcode as the region `profile/opcode/<MNEMONIC>` and every precompile call as the
+//! region `profile/precompile/<address>`, and counts how many times each one ran. The counts are
+//! committed with the [`BlockOutput`](crate::BlockOutput), while the cycles are collected by
+//! the host like any other region.
+//!
+//! An opcode region only covers the opcode itself: the code run by a `CALL` or `CREATE` is
+//! attributed to its own opcodes. Delimiting every opcode adds overhead to the enclosing regions
+//! and to the total cycle count, so profiling builds should not be used for the block-level
+//! benchmarks.
+
+use alloc::{collections::BTreeMap, string::String};
+
+use alloy_primitives::Address;
+use serde_derive::{Deserialize, Serialize};
+
+/// Prefix of the regions tracking each opcode, followed by the opcode mnemonic.
+pub const OPCODE_REGION_PREFIX: &str = "profile/opcode/";
+
+/// Prefix of the regions tracking each precompile, followed by the precompile address.
+pub const PRECOMPILE_REGION_PREFIX: &str = "profile/precompile/";
+
+/// Number of times each opcode and precompile ran while executing a block.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionCounts {
+    /// Executions of each opcode, keyed by mnemonic (e.g., `SSTORE`).
+    pub opcodes: BTreeMap<String, u64>,
+    /// Calls to each precompile, keyed by address.
+    pub precompiles: BTreeMap<Address, u64>,
+}
+
+#[cfg(feature = "profiling")]
+pub use inspector::Profiler;
+
+#[cfg(feature = "profiling")]
+mod inspector {
+    use alloc::{collections::BTreeMap, format, string::String, vec::Vec};
+    use core::marker::PhantomData;
+
+    use alloy_primitives::Address;
+    use revm::{
+        Inspector,
+        bytecode::OpCode,
+        interpreter::{CallInputs, CallOutcome, Interpreter, interpreter_types::Jumps},
+        precompile::Precompiles,
+    };
+
+    use super::{ExecutionCounts, OPCODE_REGION_PREFIX, PRECOMPILE_REGION_PREFIX};
+    use crate::Platform;
+
+    /// Inspector that tracks each opcode and precompile call as a region on platform `P`.
+    #[derive(Debug)]
+    pub struct Profiler<P: Platform> {
+        /// Region name of each opcode, computed once so that steps do not allocate.
+        opcode_regions: Vec<String>,
+        opcode_counts: [u64; 256],
+        /// Opcode whose region is currently open.
+        current_opcode: Option<u8>,
+        precompile_counts: BTreeMap<Address, u64>,
+        /// Precompile region opened by each call in progress, if the call is to a precompile.
+        call_regions: Vec<Option<String>>,
+        _platform: PhantomData<P>,
+    }
+
+    impl<P: Platform> Default for Profiler<P> {
+        fn default() -> Self {
+            let opcode_regions = (0..=u8::MAX)
+                .map(|opcode| match OpCode::new(opcode) {
+                    Some(op) => format!("{OPCODE_REGION_PREFIX}{}", op.as_str()),
+                    // Undefined opcodes are named after their byte.
+                    None => format!("{OPCODE_REGION_PREFIX}0x{opcode:02x}"),
+                })
+                .collect();
+            Self {
+                opcode_regions,
+                opcode_counts: [0; 256],
+                current_opcode: None,
+                precompile_counts: Default::default(),
+                call_regions: Vec::new(),
+                _platform: PhantomData,
+            }
+        }
+    }
+
+    impl<P: Platform> Profiler<P> {
+        /// Returns the number of times each opcode and precompile ran.
+        pub fn into_counts(self) -> ExecutionCounts {
+            let opcodes = self
+                .opcode_counts
+                .iter()
+                .zip(&self.opcode_regions)
+                .filter(|(count, _)| **count > 0)
+                .map(|(count, region)| (region[OPCODE_REGION_PREFIX.len()..].into(), *count))
+                .collect();
+            ExecutionCounts {
+                opcodes,
+                precompiles: self.precompile_counts,
+            }
+        }
+    }
+
+    impl<CTX, P: Platform> Inspector<CTX> for Profiler<P> {
+        fn step(&mut self, interp: &mut Interpreter, _context: &mut CTX) {
+            let opcode = interp.bytecode.opcode();
+            self.opcode_counts[opcode as usize] += 1;
+            self.current_opcode = Some(opcode);
+            P::region_start(self.opcode_regions[opcode as usize].as_str().into());
+        }
+
+        fn step_end(&mut self, _interp: &mut Interpreter, _context: &mut CTX) {
+            if let Some(opcode) = self.current_opcode.take() {
+                P::region_end(self.opcode_regions[opcode as usize].as_str().into());
+            }
+        }
+
+        fn call(&mut self, _context: &mut CTX, inputs: &mut CallInputs) -> Option<CallOutcome> {
+            let address = inputs.bytecode_address;
+            // Precompile addresses are never reassigned, so the latest set covers every fork.
+            let region = Precompiles::latest().contains(&address).then(|| {
+                *self.precompile_counts.entry(address).or_default() += 1;
+                let region = format!("{PRECOMPILE_REGION_PREFIX}{address}");
+                P::region_start(region.as_str().into());
+                region
+            });
+            self.call_regions.push(region);
+            None
+        }
+
+        fn call_end(
+            &mut self,
+            _context: &mut CTX,
+            _inputs: &CallInputs,
+            _outcome: &mut CallOutcome,
+        ) {
+            if let Some(region) = self.call_regions.pop().flatten() {
+                P::region_end(region.as_str().into());
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Named regions for cycle counting.
+//!
+//! Regions are delimited with a [`Region`] guard, which maps onto the native mechanism of each
+//! [`Platform`]. Whatever the zkVM, a region shows up under the same name in
+//! `WorkloadMetrics::region_cycles`, so regions can be compared across zkVMs.
+//!
+//! Nested regions are named after their path, separated by `/` (e.g., `validation/execution`).
+//! Entering the same region several times accumulates its cycles.
+
+use core::{fmt, marker::PhantomData};
+
+use crate::Platform;
+
+/// Prefix of the lines through which platforms without a native cycle tracker report the
+/// cycles of a region.
+///
+/// Each report is a single line: `<prefix><name> <cycles>`. Hosts parse these lines with
+/// `benchmark_runner::parse_region_reports`.
+pub const REGION_REPORT_PREFIX: &str = "zkevm-region-report: ";
+
+/// Name of a region, as given to [`Platform::region_start`] and [`Platform::region_end`].
+///
+/// It displays as the path of the region. The name of an indexed region, such as the region of
+/// each transaction, is only put together when it is displayed, so that naming the region
+/// allocates nothing within the regions being measured.
+#[derive(Debug, Clone, Copy)]
+pub struct RegionName<'a> {
+    path: &'a str,
+    index: Option<usize>,
+}
+
+impl<'a> From<&'a str> for RegionName<'a> {
+    fn from(path: &'a str) -> Self {
+        Self { path, index: None }
+    }
+}
+
+impl fmt::Display for RegionName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.path)?;
+        match self.index {
+            Some(index) => write!(f, "{index}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Guard that tracks the region `name` from its creation until it is dropped.
+#[derive(Debug)]
+#[must_use = "the region ends as soon as the guard is dropped"]
+pub struct Region<'a, P: Platform> {
+    name: RegionName<'a>,
+    _platform: PhantomData<P>,
+}
+
+impl<'a, P: Platform> Region<'a, P> {
+    /// Starts tracking the region `name`.
+    pub fn start(name: &'a str) -> Self {
+        Self::start_named(name.into())
+    }
+
+    /// Starts tracking the region `<prefix><index>`, e.g., `validation/execution/tx/3`.
+    pub fn start_indexed(prefix: &'a str, index: usize) -> Self {
+        Self::start_named(RegionName {
+            path: prefix,
+            index: Some(index),
+        })
+    }
+
+    fn start_named(name: RegionName<'a>) -> Self {
+        P::region_start(name);
+        Self {
+            name,
+            _platform: PhantomData,
+        }
+    }
+}
+
+impl<P: Platform> Drop for Region<'_, P> {
+    fn drop(&mut self) {
+        P::region_end(self.name);
+    }
+}
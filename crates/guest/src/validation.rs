@@ -0,0 +1,190 @@
+//! Stateless block validation, split into regions.
+//!
+//! This follows `reth_stateless::validation::stateless_validation` step by step, using the
+//! building blocks `reth_stateless` exposes, so that the cost of each phase is measured in its
+//! own region under `validation/`:
+//!
+//! - `recover_senders`: recovering the transaction signers.
+//! - `ancestors`: decoding the ancestor headers and checking they form a chain.
+//! - `header`: consensus checks on the header and block body before execution.
+//! - `witness`: verifying the witness against the parent state root.
+//! - `execution`: executing the block in the EVM. Each transaction is also tracked in its own
+//!   region, `validation/execution/tx/<index>` (see [`TRANSACTION_REGION_PREFIX`]). With the
+//!   `profiling` feature, each opcode and precompile is tracked as well (see
+//!   [`profiling`](crate::profiling)).
+//! - `post_execution`: consensus checks on the execution output (gas used, receipts, requests).
+//! - `state_root`: computing the post-state root.
+
+use alloc::{string::ToString, sync::Arc, vec::Vec};
+
+use alloy_consensus::{BlockHeader, Header};
+use alloy_primitives::B256;
+use alloy_rlp::Decodable;
+use reth_chainspec::ChainSpec;
+use reth_consensus::{Consensus, HeaderValidator};
+use reth_ethereum_consensus::{EthBeaconConsensus, validate_block_post_execution};
+use reth_ethereum_primitives::Block;
+use reth_evm::{
+    ConfigureEvm,
+    execute::{BlockExecutionError, BlockExecutor},
+};
+use reth_evm_ethereum::EthEvmConfig;
+use reth_primitives_traits::{Block as _, SealedHeader};
+use reth_revm::db::{State, states::bundle_state::BundleRetention};
+use reth_stateless::{
+    ExecutionWitness,
+    trie::StatelessTrie,
+    validation::{StatelessValidationError, compute_ancestor_hashes},
+    witness_db::WitnessDatabase,
+};
+use reth_trie_common::{HashedPostState, KeccakKeyHasher};
+
+#[cfg(feature = "profiling")]
+use crate::profiling::Profiler;
+use crate::{Platform, Region, profiling::ExecutionCounts};
+
+/// Prefix of the regions tracking each transaction, followed by the transaction's index in the
+/// block.
+pub const TRANSACTION_REGION_PREFIX: &str = "validation/execution/tx/";
+
+/// A block that passed stateless validation.
+#[derive(Debug)]
+pub struct ValidatedBlock {
+    /// Hash of the block.
+    pub hash: B256,
+    /// Gas used by each transaction of the block, in block order.
+    pub tx_gas_used: Vec<u64>,
+    /// Opcode and precompile counts of the execution, with the `profiling` feature.
+    pub profile: Option<ExecutionCounts>,
+}
+
+/// Validates `block` against the pre-state in `witness`, tracking each phase as a region on
+/// platform `P`.
+///
+/// # Errors
+///
+/// Returns the same errors as `reth_stateless::validation::stateless_validation`.
+pub fn stateless_validation<P: Platform>(
+    block: Block,
+    witness: ExecutionWitness,
+    chain_spec: Arc<ChainSpec>,
+) -> Result<ValidatedBlock, StatelessValidationError> {
+    let current_block = {
+        let _region = Region::<P>::start("validation/recover_senders");
+        block
+            .try_into_recovered()
+            .map_err(|_| StatelessValidationError::SignerRecovery)?
+    };
+
+    let (parent, ancestor_hashes) = {
+        let _region = Region::<P>::start("validation/ancestors");
+        let mut ancestor_headers: Vec<Header> = witness
+            .headers
+            .iter()
+            .map(|serialized_header| {
+                Header::decode(&mut serialized_header.as_ref())
+                    .map_err(|_| StatelessValidationError::HeaderDeserializationFailed)
+            })
+            .collect::<Result<_, _>>()?;
+        // Sort the headers by their block number to ensure that they are in ascending order.
+        ancestor_headers.sort_by_key(|header| header.number());
+
+        // Check that the ancestor headers form a contiguous chain ending at the parent.
+        let ancestor_hashes = compute_ancestor_hashes(&current_block, &ancestor_headers)?;
+        let parent = ancestor_headers
+            .pop()
+            .ok_or(StatelessValidationError::MissingAncestorHeader)?;
+        (SealedHeader::seal_slow(parent), ancestor_hashes)
+    };
+
+    {
+        let _region = Region::<P>::start("validation/header");
+        let consensus = EthBeaconConsensus::new(chain_spec.clone());
+        consensus
+            .validate_header(current_block.sealed_header())
+            .and_then(|()| {
+                consensus.validate_header_against_parent(current_block.sealed_header(), &parent)
+            })
+            .and_then(|()| consensus.validate_block_pre_execution(current_block.sealed_block()))
+            .map_err(StatelessValidationError::ConsensusValidationFailed)?;
+    }
+
+    let (mut trie, bytecode) = {
+        let _region = Region::<P>::start("validation/witness");
+        StatelessTrie::new(&witness, parent.state_root)?
+    };
+
+    // Same steps as `BasicBlockExecutor::execute`, unrolled to track each transaction.
+    let (output, bundle, tx_gas_used, profile) = {
+        let _region = Region::<P>::start("validation/execution");
+        let mut db = State::builder()
+            .with_database(WitnessDatabase::new(&trie, bytecode, ancestor_hashes))
+            .with_bundle_update()
+            .without_state_clear()
+            .build();
+        let evm_config = EthEvmConfig::new(chain_spec.clone());
+        #[cfg(feature = "profiling")]
+        let mut profiler = Profiler::<P>::default();
+        #[cfg(feature = "profiling")]
+        let mut executor = {
+            let evm_env = evm_config.evm_env(current_block.header());
+            let evm = evm_config.evm_with_env_and_inspector(&mut db, evm_env, &mut profiler);
+            let ctx = evm_config.context_for_block(current_block.sealed_block());
+            evm_config.create_executor(evm, ctx)
+        };
+        #[cfg(not(feature = "profiling"))]
+        let mut executor = evm_config.executor_for_block(&mut db, current_block.sealed_block());
+
+        let execution_failed = |err: BlockExecutionError| {
+            StatelessValidationError::StatelessExecutionFailed(err.to_string())
+        };
+        executor
+            .apply_pre_execution_changes()
+            .map_err(execution_failed)?;
+        let mut tx_gas_used = Vec::with_capacity(current_block.body().transactions.len());
+        for (index, tx) in current_block.transactions_recovered().enumerate() {
+            let _region = Region::<P>::start_indexed(TRANSACTION_REGION_PREFIX, index);
+            tx_gas_used.push(executor.execute_transaction(tx).map_err(execution_failed)?);
+        }
+        let output = executor
+            .apply_post_execution_changes()
+            .map_err(execution_failed)?;
+
+        #[cfg(feature = "profiling")]
+        let profile = Some(profiler.into_counts());
+        #[cfg(not(feature = "profiling"))]
+        let profile = None;
+
+        db.merge_transitions(BundleRetention::Reverts);
+        (output, db.take_bundle(), tx_gas_used, profile)
+    };
+
+    {
+        let _region = Region::<P>::start("validation/post_execution");
+        validate_block_post_execution(
+            &current_block,
+            &chain_spec,
+            &output.receipts,
+            &output.requests,
+        )
+        .map_err(StatelessValidationError::ConsensusValidationFailed)?;
+    }
+
+    let state_root = {
+        let _region = Region::<P>::start("validation/state_root");
+        let hashed_state = HashedPostState::from_bundle_state::<KeccakKeyHasher>(&bundle.state);
+        trie.calculate_state_root(hashed_state)?
+    };
+    if state_root != current_block.state_root {
+        return Err(StatelessValidationError::PostStateRootMismatch {
+            got: state_root,
+            expected: current_block.state_root,
+        });
+    }
+
+    Ok(ValidatedBlock {
+        hash: current_block.hash_slow(),
+        tx_gas_used,
+        profile,
+    })
+}
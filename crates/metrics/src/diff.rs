@@ -0,0 +1,308 @@
+//! Comparison of two result sets, to detect regressions.
+//!
+//! Workloads are matched by zkVM and name. For every matched workload, the change in total
+//! cycles and in the inclusive cycles of every region is reported, and [`DiffThresholds`]
+//! decide which increases count as regressions. Workloads whose cycles were not measured are
+//! listed apart, as are workloads that no longer succeed: a block the candidate wrongly rejects
+//! stops early, which would otherwise look like an improvement.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{ResultSet, WorkloadStatus};
+
+/// Cycle counts of the same measurement in the baseline and candidate results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CyclesChange {
+    /// Cycles in the baseline results.
+    pub before: u64,
+    /// Cycles in the candidate results.
+    pub after: u64,
+}
+
+impl CyclesChange {
+    /// Absolute change in cycles, negative for improvements.
+    #[must_use]
+    pub fn absolute(&self) -> i128 {
+        i128::from(self.after) - i128::from(self.before)
+    }
+
+    /// Change relative to the baseline (e.g., `0.1` for 10% more cycles), or `None` if the
+    /// baseline is zero.
+    #[must_use]
+    pub fn relative(&self) -> Option<f64> {
+        crate::ratio(self.after, self.before).map(|ratio| ratio - 1.0)
+    }
+}
+
+/// Changes of a workload present in both result sets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkloadDiff {
+    /// zkVM the workload ran on.
+    pub zkvm: String,
+    /// Name of the workload.
+    pub name: String,
+    /// Change in total cycles.
+    pub total: CyclesChange,
+    /// Change in the inclusive cycles of each region recorded in either result set.
+    ///
+    /// A region missing from one side counts as zero cycles on that side.
+    pub regions: BTreeMap<String, CyclesChange>,
+}
+
+/// Differences between a baseline and a candidate result set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResultsDiff {
+    /// Workloads present in both result sets, in baseline order.
+    pub workloads: Vec<WorkloadDiff>,
+    /// `(zkvm, name)` of the workloads only present in the baseline.
+    pub only_in_baseline: Vec<(String, String)>,
+    /// `(zkvm, name)` of the workloads only present in the candidate.
+    pub only_in_candidate: Vec<(String, String)>,
+    /// `(zkvm, name)` of the workloads present in both result sets whose cycles the zkVM did not
+    /// report on either side, so they cannot be compared.
+    pub unmeasured: Vec<(String, String)>,
+    /// `(zkvm, name)` of the workloads that succeeded in the baseline but not in the candidate.
+    ///
+    /// Their cycles are not compared: they measure a different execution.
+    pub broken: Vec<(String, String)>,
+}
+
+/// Thresholds above which an increase in cycles is a regression.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiffThresholds {
+    /// Maximum relative increase of the total cycles of a workload (e.g., `0.05` for 5%).
+    pub total: f64,
+    /// Maximum relative increase of the cycles of a region, or `None` to not check regions.
+    pub region: Option<f64>,
+    /// Increases of fewer cycles than this are never regressions, to ignore noise in small
+    /// measurements.
+    pub min_cycles: u64,
+    /// Whether workloads of the baseline may be missing from the candidate.
+    pub allow_missing: bool,
+}
+
+impl Default for DiffThresholds {
+    fn default() -> Self {
+        Self {
+            total: 0.05,
+            region: None,
+            min_cycles: 0,
+            allow_missing: false,
+        }
+    }
+}
+
+/// An increase in cycles that exceeds the thresholds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Regression {
+    /// zkVM the workload ran on.
+    pub zkvm: String,
+    /// Name of the workload.
+    pub name: String,
+    /// Region that regressed, or `None` for the total cycles.
+    pub region: Option<String>,
+    /// Cycles before and after.
+    pub change: CyclesChange,
+}
+
+impl ResultsDiff {
+    /// Matches the workloads of `baseline` and `candidate` and computes their changes.
+    #[must_use]
+    pub fn new(baseline: &ResultSet, candidate: &ResultSet) -> Self {
+        let mut diff = Self::default();
+        for before in &baseline.results {
+            let Some(after) = candidate.get(&before.zkvm, &before.metrics.name) else {
+                diff.only_in_baseline
+                    .push((before.zkvm.clone(), before.metrics.name.clone()));
+                continue;
+            };
+
+            if before.metrics.status == WorkloadStatus::Success
+                && after.metrics.status != WorkloadStatus::Success
+            {
+                diff.broken
+                    .push((before.zkvm.clone(), before.metrics.name.clone()));
+                continue;
+            }
+
+            if before.metrics.cycles_unsupported || after.metrics.cycles_unsupported {
+                diff.unmeasured
+                    .push((before.zkvm.clone(), before.metrics.name.clone()));
+                continue;
+            }
+
+            let before_regions = before.metrics.region_cycles.flatten();
+            let after_regions = after.metrics.region_cycles.flatten();
+            let paths: BTreeSet<&String> =
+                before_regions.keys().chain(after_regions.keys()).collect();
+            let regions = paths
+                .into_iter()
+                .map(|path| {
+                    let change = CyclesChange {
+                        before: before_regions.get(path).copied().unwrap_or_default(),
+                        after: after_regions.get(path).copied().unwrap_or_default(),
+                    };
+                    (path.clone(), change)
+                })
+                .collect();
+
+            diff.workloads.push(WorkloadDiff {
+                zkvm: before.zkvm.clone(),
+                name: before.metrics.name.clone(),
+                total: CyclesChange {
+                    before: before.metrics.total_num_cycles,
+                    after: after.metrics.total_num_cycles,
+                },
+                regions,
+            });
+        }
+        diff.only_in_candidate = candidate
+            .results
+            .iter()
+            .filter(|after| baseline.get(&after.zkvm, &after.metrics.name).is_none())
+            .map(|after| (after.zkvm.clone(), after.metrics.name.clone()))
+            .collect();
+        diff
+    }
+
+    /// Returns whether the candidate passes the comparison: no change exceeds `thresholds`, no
+    /// workload stopped succeeding and, unless `thresholds` allow it, none is missing.
+    #[must_use]
+    pub fn passes(&self, thresholds: &DiffThresholds) -> bool {
+        self.regressions(thresholds).is_empty()
+            && self.broken.is_empty()
+            && (thresholds.allow_missing || self.only_in_baseline.is_empty())
+    }
+
+    /// Returns the changes that exceed `thresholds`.
+    ///
+    /// Regions that did not exist in the baseline have no relative change, so they are never
+    /// regressions.
+    #[must_use]
+    pub fn regressions(&self, thresholds: &DiffThresholds) -> Vec<Regression> {
+        let exceeds = |change: &CyclesChange, threshold: f64| {
+            change.absolute() >= i128::from(thresholds.min_cycles.max(1))
+                && change
+                    .relative()
+                    .is_some_and(|relative| relative > threshold)
+        };
+
+        let mut regressions = Vec::new();
+        for workload in &self.workloads {
+            let regression = |region: Option<&String>, change: &CyclesChange| Regression {
+                zkvm: workload.zkvm.clone(),
+                name: workload.name.clone(),
+                region: region.cloned(),
+                change: *change,
+            };
+            if exceeds(&workload.total, thresholds.total) {
+                regressions.push(regression(None, &workload.total));
+            }
+            if let Some(threshold) = thresholds.region {
+                regressions.extend(
+                    workload
+                        .regions
+                        .iter()
+                        .filter(|(_, change)| exceeds(change, threshold))
+                        .map(|(path, change)| regression(Some(path), change)),
+                );
+            }
+        }
+        regressions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WorkloadMetrics;
+
+    #[test]
+    fn regressions_exceed_thresholds() {
+        let baseline = ResultSet::for_test([
+            (
+                "succinct",
+                WorkloadMetrics::for_test("fft", 1_000, &[("compute", 800)]),
+            ),
+            ("succinct", WorkloadMetrics::for_test("aes", 2_000, &[])),
+        ]);
+        let candidate = ResultSet::for_test([
+            (
+                "succinct",
+                WorkloadMetrics::for_test("fft", 1_200, &[("compute", 1_000)]),
+            ),
+            ("succinct", WorkloadMetrics::for_test("sha", 500, &[])),
+        ]);
+
+        let diff = ResultsDiff::new(&baseline, &candidate);
+        assert_eq!(diff.workloads.len(), 1);
+        assert_eq!(
+            diff.only_in_baseline,
+            [("succinct".to_string(), "aes".to_string())]
+        );
+        assert_eq!(
+            diff.only_in_candidate,
+            [("succinct".to_string(), "sha".to_string())]
+        );
+        let fft = &diff.workloads[0];
+        assert_eq!(fft.total.absolute(), 200);
+        assert_eq!(fft.regions["compute"].after, 1_000);
+
+        let thresholds = DiffThresholds {
+            total: 0.1,
+            region: Some(0.3),
+            min_cycles: 0,
+            allow_missing: false,
+        };
+        let regressions = diff.regressions(&thresholds);
+        assert_eq!(regressions.len(), 1);
+        assert_eq!(regressions[0].region, None);
+
+        // Compute went from 800 to 1000 cycles, a 25% increase.
+        let thresholds = DiffThresholds {
+            region: Some(0.2),
+            ..thresholds
+        };
+        assert_eq!(diff.regressions(&thresholds).len(), 2);
+
+        let thresholds = DiffThresholds {
+            min_cycles: 500,
+            ..thresholds
+        };
+        assert!(diff.regressions(&thresholds).is_empty());
+
+        // The baseline's aes workload is missing from the candidate.
+        assert!(!diff.passes(&thresholds));
+        let thresholds = DiffThresholds {
+            allow_missing: true,
+            ..thresholds
+        };
+        assert!(diff.passes(&thresholds));
+    }
+
+    #[test]
+    fn new_failures_are_regressions() {
+        let mut rejected = WorkloadMetrics::for_test("block", 100, &[]);
+        rejected.status = WorkloadStatus::CorrectnessFailure;
+        let baseline =
+            ResultSet::for_test([("sp1", WorkloadMetrics::for_test("block", 1_000, &[]))]);
+        let candidate = ResultSet::for_test([("sp1", rejected)]);
+
+        let diff = ResultsDiff::new(&baseline, &candidate);
+        assert!(diff.workloads.is_empty());
+        assert_eq!(diff.broken, [("sp1".to_string(), "block".to_string())]);
+        assert!(!diff.passes(&DiffThresholds::default()));
+    }
+
+    #[test]
+    fn unmeasured_workloads_are_listed_apart() {
+        let mut unmeasured = WorkloadMetrics::for_test("fft", 0, &[]);
+        unmeasured.cycles_unsupported = true;
+        let baseline = ResultSet::for_test([("openvm", WorkloadMetrics::for_test("fft", 0, &[]))]);
+        let candidate = ResultSet::for_test([("openvm", unmeasured)]);
+
+        let diff = ResultsDiff::new(&baseline, &candidate);
+        assert!(diff.workloads.is_empty());
+        assert_eq!(diff.unmeasured, [("openvm".to_string(), "fft".to_string())]);
+    }
+}
@@ -0,0 +1,253 @@
+//! Export of a [`ResultSet`] as a flat table, for analysis with dataframe and spreadsheet tools.
+//!
+//! Every workload becomes one row for the whole block, with no region, followed by one row per
+//! region of its [`RegionTree`](crate::RegionTree), parents first. The table can be written as
+//! CSV or as Parquet; Parquet keeps the column types, so counts load back as unsigned integers
+//! and missing values as nulls. Cycles a zkVM did not report are missing values.
+
+use std::{fs::File, path::Path, sync::Arc};
+
+use arrow_array::{ArrayRef, RecordBatch, StringArray, UInt64Array};
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use serde_derive::Serialize;
+
+use crate::{MetricsError, ResultSet, WorkloadStatus};
+
+/// A row of the exported table: the cycles of one region of one block on one zkVM.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ExportRow {
+    /// zkVM the workload ran on.
+    pub zkvm: String,
+    /// Corpus the workload belongs to.
+    pub corpus: String,
+    /// Name of the workload, which identifies the block within its corpus.
+    pub block: String,
+    /// Path of the region, or `None` for the row covering the whole block.
+    pub region: Option<String>,
+    /// Outcome of the workload.
+    pub status: WorkloadStatus,
+    /// Inclusive cycles of the region, or the total cycles of the workload for the block row.
+    pub cycles: Option<u64>,
+    /// Cycles of the region outside of its nested regions, or the cycles outside of any region
+    /// for the block row.
+    pub self_cycles: Option<u64>,
+    /// Total cycles of the workload, if the zkVM reported them.
+    pub total_cycles: Option<u64>,
+    /// Gas used by the block, if recorded.
+    pub gas_used: Option<u64>,
+    /// Number of transactions in the block, if recorded.
+    pub tx_count: Option<u64>,
+    /// Size of the execution witness in bytes, if recorded.
+    pub witness_size: Option<u64>,
+}
+
+/// Flattens `results` into one row per block and per region of each block.
+#[must_use]
+pub fn export_rows(results: &ResultSet) -> Vec<ExportRow> {
+    let mut rows = Vec::new();
+    for result in &results.results {
+        let metrics = &result.metrics;
+        let total_cycles = metrics.measured_cycles();
+        let row =
+            |region: Option<String>, cycles: Option<u64>, self_cycles: Option<u64>| ExportRow {
+                zkvm: result.zkvm.clone(),
+                corpus: result.corpus.clone(),
+                block: metrics.name.clone(),
+                region,
+                status: metrics.status,
+                cycles,
+                self_cycles,
+                total_cycles,
+                gas_used: metrics.block.map(|block| block.gas_used),
+                tx_count: metrics.block.map(|block| block.tx_count as u64),
+                witness_size: metrics.block.map(|block| block.witness_size as u64),
+            };
+
+        let in_regions: u64 = metrics
+            .region_cycles
+            .roots()
+            .map(|root| root.inclusive_cycles())
+            .sum();
+        rows.push(row(
+            None,
+            total_cycles,
+            total_cycles.map(|cycles| cycles.saturating_sub(in_regions)),
+        ));
+        rows.extend(metrics.region_cycles.iter().map(|node| {
+            row(
+                Some(node.path().to_string()),
+                Some(node.inclusive_cycles()),
+                Some(node.self_cycles()),
+            )
+        }));
+    }
+    rows
+}
+
+/// Writes `rows` as a CSV file with a header row. Missing values are left empty.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn write_csv<P: AsRef<Path>>(rows: &[ExportRow], path: P) -> Result<(), MetricsError> {
+    let mut writer = csv::Writer::from_path(path)?;
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes `rows` as a Parquet file, with counts as unsigned 64-bit integers and missing values
+/// as nulls.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn write_parquet<P: AsRef<Path>>(rows: &[ExportRow], path: P) -> Result<(), MetricsError> {
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("zkvm", DataType::Utf8, false),
+        Field::new("corpus", DataType::Utf8, false),
+        Field::new("block", DataType::Utf8, false),
+        Field::new("region", DataType::Utf8, true),
+        Field::new("status", DataType::Utf8, false),
+        Field::new("cycles", DataType::UInt64, true),
+        Field::new("self_cycles", DataType::UInt64, true),
+        Field::new("total_cycles", DataType::UInt64, true),
+        Field::new("gas_used", DataType::UInt64, true),
+        Field::new("tx_count", DataType::UInt64, true),
+        Field::new("witness_size", DataType::UInt64, true),
+    ]));
+
+    let strings = |column: fn(&ExportRow) -> Option<&str>| -> ArrayRef {
+        Arc::new(rows.iter().map(column).collect::<StringArray>())
+    };
+    let counts = |column: fn(&ExportRow) -> Option<u64>| -> ArrayRef {
+        Arc::new(rows.iter().map(column).collect::<UInt64Array>())
+    };
+    let columns = vec![
+        strings(|row| Some(&row.zkvm)),
+        strings(|row| Some(&row.corpus)),
+        strings(|row| Some(&row.block)),
+        strings(|row| row.region.as_deref()),
+        strings(|row| Some(status_name(row.status))),
+        counts(|row| row.cycles),
+        counts(|row| row.self_cycles),
+        counts(|row| row.total_cycles),
+        counts(|row| row.gas_used),
+        counts(|row| row.tx_count),
+        counts(|row| row.witness_size),
+    ];
+    let batch = RecordBatch::try_new(Arc::clone(&schema), columns)?;
+
+    let mut writer = ArrowWriter::try_new(File::create(path)?, schema, None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+/// Name of `status` as it appears in the metrics JSON.
+const fn status_name(status: WorkloadStatus) -> &'static str {
+    match status {
+        WorkloadStatus::Success => "success",
+        WorkloadStatus::CorrectnessFailure => "correctness_failure",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlockMetrics, WorkloadMetrics};
+    use arrow_array::Array;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::fs;
+
+    fn results() -> ResultSet {
+        let mut fft = WorkloadMetrics::for_test("fft", 1_000, &[("compute", 800), ("setup", 200)]);
+        fft.block = Some(BlockMetrics {
+            gas_used: 71_000,
+            tx_count: 2,
+            witness_size: 4_096,
+        });
+        let aes = WorkloadMetrics::for_test("aes", 2_000, &[]);
+        ResultSet::for_test([("succinct", fft), ("succinct", aes)])
+    }
+
+    #[test]
+    fn blocks_and_regions_become_rows() {
+        let rows = export_rows(&results());
+        // A block row and two region rows for fft, a block row for aes.
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[0].region, None);
+        assert_eq!(
+            (rows[0].cycles, rows[0].self_cycles),
+            (Some(1_000), Some(0))
+        );
+        assert_eq!(rows[0].gas_used, Some(71_000));
+        assert_eq!(rows[1].region.as_deref(), Some("compute"));
+        assert_eq!(rows[1].total_cycles, Some(1_000));
+        assert_eq!(rows[3].block, "aes");
+        assert_eq!(rows[3].self_cycles, Some(2_000));
+        assert_eq!(rows[3].gas_used, None);
+    }
+
+    #[test]
+    fn unmeasured_cycles_are_missing_values() {
+        let mut unmeasured = WorkloadMetrics::for_test("fft", 0, &[]);
+        unmeasured.cycles_unsupported = true;
+        let rows = export_rows(&ResultSet::for_test([("openvm", unmeasured)]));
+        assert_eq!(rows.len(), 1);
+        assert_eq!(
+            (rows[0].cycles, rows[0].self_cycles, rows[0].total_cycles),
+            (None, None, None)
+        );
+    }
+
+    #[test]
+    fn rows_are_written_as_csv_and_parquet() -> Result<(), MetricsError> {
+        let rows = export_rows(&results());
+        let dir = tempfile::tempdir()?;
+
+        let csv_path = dir.path().join("metrics.csv");
+        write_csv(&rows, &csv_path)?;
+        let csv = fs::read_to_string(&csv_path)?;
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some(
+                "zkvm,corpus,block,region,status,cycles,self_cycles,total_cycles,gas_used,tx_count,witness_size"
+            )
+        );
+        assert_eq!(
+            lines.next(),
+            Some("succinct,corpus,fft,,success,1000,0,1000,71000,2,4096")
+        );
+        assert_eq!(
+            lines.nth(2),
+            Some("succinct,corpus,aes,,success,2000,2000,2000,,,")
+        );
+
+        let parquet_path = dir.path().join("metrics.parquet");
+        write_parquet(&rows, &parquet_path)?;
+        let reader =
+            ParquetRecordBatchReaderBuilder::try_new(File::open(&parquet_path)?)?.build()?;
+        let batches = reader.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            batches.iter().map(|batch| batch.num_rows()).sum::<usize>(),
+            4
+        );
+        let batch = &batches[0];
+        assert_eq!(
+            batch.schema().field_with_name("cycles")?.data_type(),
+            &DataType::UInt64
+        );
+        let gas_used = batch
+            .column_by_name("gas_used")
+            .and_then(|column| column.as_any().downcast_ref::<UInt64Array>())
+            .expect("gas_used is a u64 column");
+        assert_eq!(gas_used.value(0), 71_000);
+        assert!(gas_used.is_null(3));
+        Ok(())
+    }
+}
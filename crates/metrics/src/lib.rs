@@ -1,20 +1,161 @@
 #![doc = include_str!("../README.md")]
 
+use serde::{Serialize, de::DeserializeOwned};
 use serde_derive::{Deserialize, Serialize};
-use std::{collections::HashMap, fs, io, path::Path};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+};
 use thiserror::Error;
 
+pub mod diff;
+#[cfg(feature = "cli")]
+pub mod export;
+mod manifest;
+mod profile;
+mod regions;
+pub mod report;
+mod results;
+pub mod savings;
+mod schema;
+pub mod summary;
+pub mod table;
+
+pub use manifest::{RUN_MANIFEST_FILE_NAME, RunManifest, results_name};
+pub use profile::{BlockProfile, OperationProfile};
+pub use regions::{REGION_PATH_SEPARATOR, RegionNode, RegionTree};
+pub use results::{PROFILE_FILE_SUFFIX, ResultSet, WorkloadResult};
+pub use schema::SCHEMA_VERSION;
+
 /// Cycle-count metrics for a particular workload.
 ///
-/// Stores the total cycle count and a breakdown of cycle count per named region.
+/// Stores the total cycle count and a breakdown of cycle count per named region, the outcome
+/// of the workload, and optional sections with further measurements.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct WorkloadMetrics {
+    /// Version of the schema the metrics were written with, see [`SCHEMA_VERSION`].
+    pub schema_version: u32,
     /// Name of the workload (e.g., "fft", "aes").
     pub name: String,
     /// Total number of cycles for the entire workload execution.
     pub total_num_cycles: u64,
-    /// Region-specific cycles, mapping region names (e.g., "setup", "compute") to their cycle counts.
-    pub region_cycles: HashMap<String, u64>,
+    /// Region-specific cycles, as a tree of regions nested by path (e.g., "validation/execution").
+    ///
+    /// Serialized as a map from region paths to their inclusive cycle counts.
+    pub region_cycles: RegionTree,
+    /// Whether the zkVM does not report cycle counts.
+    ///
+    /// `total_num_cycles` and `region_cycles` are then left empty rather than measured, and
+    /// aggregations leave the workload out.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cycles_unsupported: bool,
+    /// Outcome of the workload.
+    pub status: WorkloadStatus,
+    /// Description of what went wrong, for workloads that did not succeed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Outcome of a block that the fixture expects to be rejected.
+    ///
+    /// `None` for blocks that are expected to be valid. For rejected blocks, `total_num_cycles`
+    /// is the cost of detecting the invalid block.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejection: Option<RejectionMetrics>,
+    /// Per-transaction breakdown of the block execution, in block order.
+    ///
+    /// Empty when the guest did not report per-transaction data (e.g., the block was rejected).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transactions: Vec<TransactionMetrics>,
+    /// Properties of the block the workload validated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block: Option<BlockMetrics>,
+    /// Details of the execution reported by the zkVM, for zkVMs that provide them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execution_report: Option<ExecutionReportMetrics>,
+}
+
+/// Outcome of a workload.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkloadStatus {
+    /// The guest produced the expected outputs: it accepted a valid block with the right public
+    /// values, or rejected a block that is expected to be invalid.
+    Success,
+    /// The guest's outputs did not match the expected values.
+    ///
+    /// The workload still reports its cycle counts, but those cycles were not spent validating
+    /// the input the benchmark intended to measure.
+    CorrectnessFailure,
+}
+
+/// Details of an execution reported by the zkVM, beyond cycle counts.
+///
+/// Syscall counts include the zkVM precompiles (e.g., keccak, secp256k1 and bn254), so comparing
+/// them with the opcode counts shows how much of a block's cost is precompile work rather than
+/// plain instruction execution.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExecutionReportMetrics {
+    /// Number of times each instruction of the zkVM ran, keyed by mnemonic.
+    pub opcode_counts: BTreeMap<String, u64>,
+    /// Number of times each syscall was invoked, keyed by syscall name.
+    pub syscall_counts: BTreeMap<String, u64>,
+    /// Number of distinct memory addresses touched during execution.
+    pub touched_memory_addresses: u64,
+    /// Proving gas estimated by the zkVM, if it computed one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas: Option<u64>,
+}
+
+/// Size of the block a workload validated, used to normalize its cycle counts.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BlockMetrics {
+    /// Gas used by the block, as recorded in its header.
+    pub gas_used: u64,
+    /// Number of transactions in the block.
+    pub tx_count: usize,
+    /// Size of the execution witness, in bytes.
+    pub witness_size: usize,
+}
+
+/// Cycles and gas of a single transaction within a block.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TransactionMetrics {
+    /// Index of the transaction in the block.
+    pub index: usize,
+    /// Gas used by the transaction.
+    pub gas_used: u64,
+    /// Cycles spent executing the transaction.
+    pub cycles: u64,
+}
+
+/// Outcome of executing a block that is expected to be rejected.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RejectionMetrics {
+    /// Exception the fixture expects the block to be rejected with
+    /// (e.g., "`TransactionException.INTRINSIC_GAS_TOO_LOW`").
+    pub expected_exception: String,
+    /// Validation error reported by the guest, or `None` if the guest accepted the block.
+    pub error: Option<String>,
+    /// Whether the guest rejected the block as expected.
+    pub matched: bool,
+}
+
+impl TransactionMetrics {
+    /// Cycles spent per unit of gas used by the transaction, or `None` if it used no gas.
+    #[must_use]
+    pub fn cycles_per_gas(&self) -> Option<f64> {
+        per_gas(self.cycles, self.gas_used)
+    }
+}
+
+/// Divides `cycles` by `gas_used`, or returns `None` if no gas was used.
+fn per_gas(cycles: u64, gas_used: u64) -> Option<f64> {
+    ratio(cycles, gas_used)
+}
+
+/// Divides `numerator` by `denominator`, or returns `None` if `denominator` is zero.
+fn ratio(numerator: u64, denominator: u64) -> Option<f64> {
+    (denominator > 0).then(|| numerator as f64 / denominator as f64)
 }
 
 /// Errors that can occur during metrics processing.
@@ -27,19 +168,96 @@ pub enum MetricsError {
     /// Error during file system I/O operations.
     #[error("I/O error: {0}")]
     Io(#[from] io::Error),
+
+    /// The metrics were written with a newer schema than this crate supports.
+    #[error("unsupported schema version {0}, the latest supported is {SCHEMA_VERSION}")]
+    UnsupportedSchemaVersion(u32),
+
+    /// The `schema_version` field does not hold a version number.
+    #[error("invalid schema version: {0}")]
+    InvalidSchemaVersion(String),
+
+    /// Error while writing a CSV file.
+    #[cfg(feature = "cli")]
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+
+    /// Error while building the columns of a Parquet file.
+    #[cfg(feature = "cli")]
+    #[error("Arrow error: {0}")]
+    Arrow(#[from] arrow_schema::ArrowError),
+
+    /// Error while writing a Parquet file.
+    #[cfg(feature = "cli")]
+    #[error("Parquet error: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
+
+    /// Error while processing a particular file or directory.
+    #[error("{}: {source}", path.display())]
+    File {
+        /// Path of the file or directory.
+        path: PathBuf,
+        /// Error that occurred.
+        #[source]
+        source: Box<Self>,
+    },
 }
 
 impl MetricsError {
+    /// Attaches the `path` of the file or directory the error occurred on.
+    #[must_use]
+    pub fn in_file(self, path: &Path) -> Self {
+        Self::File {
+            path: path.to_path_buf(),
+            source: Box::new(self),
+        }
+    }
+
     #[cfg(test)]
     fn into_serde_err(self) -> serde_json::Error {
         match self {
             MetricsError::Serde(e) => e,
             MetricsError::Io(e) => panic!("unexpected IO error in test: {e}"),
+            e => panic!("unexpected error in test: {e}"),
         }
     }
 }
 
 impl WorkloadMetrics {
+    /// Total cycles of the workload, or `None` if the zkVM does not report cycle counts.
+    #[must_use]
+    pub const fn measured_cycles(&self) -> Option<u64> {
+        if self.cycles_unsupported {
+            None
+        } else {
+            Some(self.total_num_cycles)
+        }
+    }
+
+    /// Total cycles per unit of gas used by the block.
+    ///
+    /// Returns `None` if the zkVM does not report cycle counts, the block properties were not
+    /// recorded or the block used no gas.
+    #[must_use]
+    pub fn cycles_per_gas(&self) -> Option<f64> {
+        per_gas(self.measured_cycles()?, self.block?.gas_used)
+    }
+
+    /// Cycles of each region per unit of gas used by the block.
+    ///
+    /// Returns an empty map if the block properties were not recorded or the block used no gas.
+    #[must_use]
+    pub fn region_cycles_per_gas(&self) -> HashMap<String, f64> {
+        let Some(block) = self.block else {
+            return HashMap::new();
+        };
+        self.region_cycles
+            .flatten()
+            .into_iter()
+            .filter_map(|(name, cycles)| Some((name, per_gas(cycles, block.gas_used)?)))
+            .collect()
+    }
+
     /// Serializes a list of `WorkloadMetrics` into a JSON string.
     ///
     /// # Errors
@@ -51,11 +269,17 @@ impl WorkloadMetrics {
 
     /// Deserializes a list of `WorkloadMetrics` from a JSON string.
     ///
+    /// Entries written with an older schema are migrated to [`SCHEMA_VERSION`].
+    ///
     /// # Errors
     ///
     /// Returns `MetricsError::Serde` if deserialization fails.
+    /// Returns `MetricsError::UnsupportedSchemaVersion` or `MetricsError::InvalidSchemaVersion`
+    /// if an entry has a schema version that cannot be migrated.
     pub fn from_json(json: &str) -> Result<Vec<Self>, MetricsError> {
-        serde_json::from_str(json).map_err(MetricsError::from)
+        let mut items: Vec<serde_json::Value> = serde_json::from_str(json)?;
+        schema::migrate(&mut items)?;
+        serde_json::from_value(items.into()).map_err(MetricsError::from)
     }
 
     /// Serializes `items` using JSON pretty-print and writes them to `path` atomically.
@@ -68,27 +292,65 @@ impl WorkloadMetrics {
     /// Returns `MetricsError::Io` if any filesystem operation fails.
     /// Returns `MetricsError::Serde` if JSON serialization fails.
     pub fn to_path<P: AsRef<Path>>(path: P, items: &[Self]) -> Result<(), MetricsError> {
-        let path = path.as_ref();
-
-        if let Some(parent) = path.parent() {
-            // `create_dir_all` is a no-op when the dirs are already there.
-            std::fs::create_dir_all(parent)?;
-        }
-        let json = serde_json::to_string_pretty(items)?;
-        fs::write(path, json)?;
-
-        Ok(())
+        write_json(path.as_ref(), items)
     }
 
     /// Reads the file at `path` and deserializes a `Vec<WorkloadMetrics>` from its JSON content.
     ///
+    /// Files written with an older schema are migrated to [`SCHEMA_VERSION`], so historical
+    /// results keep loading.
+    ///
     /// # Errors
     ///
     /// Returns `MetricsError::Io` if reading the file fails.
     /// Returns `MetricsError::Serde` if JSON deserialization fails.
+    /// Returns `MetricsError::UnsupportedSchemaVersion` or `MetricsError::InvalidSchemaVersion`
+    /// if an entry has a schema version that cannot be migrated.
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Vec<Self>, MetricsError> {
-        let contents = fs::read_to_string(path)?;
-        Ok(serde_json::from_str(&contents)?)
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+}
+
+/// Serializes `items` using JSON pretty-print and writes them to `path`, creating parent
+/// directories if they are missing.
+fn write_json<T: Serialize>(path: &Path, items: &[T]) -> Result<(), MetricsError> {
+    if let Some(parent) = path.parent() {
+        // `create_dir_all` is a no-op when the dirs are already there.
+        std::fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_string_pretty(items)?;
+    fs::write(path, json)?;
+
+    Ok(())
+}
+
+/// Reads the file at `path` and deserializes a list of `T` from its JSON content.
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>, MetricsError> {
+    let contents = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+#[cfg(test)]
+impl WorkloadMetrics {
+    /// A successful workload of `total_num_cycles` cycles, spent in `regions` as
+    /// `(path, cycles)`, without any of the optional measurements.
+    pub(crate) fn for_test(name: &str, total_num_cycles: u64, regions: &[(&str, u64)]) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            name: name.into(),
+            total_num_cycles,
+            region_cycles: regions
+                .iter()
+                .map(|&(path, cycles)| (path.to_string(), cycles))
+                .collect(),
+            cycles_unsupported: false,
+            status: WorkloadStatus::Success,
+            error: None,
+            rejection: None,
+            transactions: Vec::new(),
+            block: None,
+            execution_report: None,
+        }
     }
 }
 
@@ -102,22 +364,65 @@ mod tests {
     fn sample() -> Vec<WorkloadMetrics> {
         vec![
             WorkloadMetrics {
+                schema_version: SCHEMA_VERSION,
                 name: "fft".into(),
                 total_num_cycles: 1_000,
-                region_cycles: HashMap::from_iter([
+                region_cycles: RegionTree::from_iter([
                     ("setup".to_string(), 100),
                     ("compute".to_string(), 800),
                     ("teardown".to_string(), 100),
                 ]),
+                cycles_unsupported: false,
+                status: WorkloadStatus::Success,
+                error: None,
+                rejection: None,
+                transactions: vec![
+                    TransactionMetrics {
+                        index: 0,
+                        gas_used: 21_000,
+                        cycles: 300,
+                    },
+                    TransactionMetrics {
+                        index: 1,
+                        gas_used: 50_000,
+                        cycles: 500,
+                    },
+                ],
+                block: Some(BlockMetrics {
+                    gas_used: 71_000,
+                    tx_count: 2,
+                    witness_size: 4_096,
+                }),
+                execution_report: Some(ExecutionReportMetrics {
+                    opcode_counts: BTreeMap::from([
+                        ("add".to_string(), 600),
+                        ("ecall".to_string(), 10),
+                    ]),
+                    syscall_counts: BTreeMap::from([("KECCAK_PERMUTE".to_string(), 10)]),
+                    touched_memory_addresses: 2_048,
+                    gas: Some(5_000),
+                }),
             },
             WorkloadMetrics {
+                schema_version: SCHEMA_VERSION,
                 name: "aes".into(),
                 total_num_cycles: 2_000,
-                region_cycles: HashMap::from_iter([
+                region_cycles: RegionTree::from_iter([
                     ("init".to_string(), 200),
                     ("encrypt".to_string(), 1_600),
                     ("final".to_string(), 200),
                 ]),
+                cycles_unsupported: false,
+                status: WorkloadStatus::Success,
+                error: None,
+                rejection: Some(RejectionMetrics {
+                    expected_exception: "TransactionException.INSUFFICIENT_ACCOUNT_FUNDS".into(),
+                    error: Some("stateless execution failed".into()),
+                    matched: true,
+                }),
+                transactions: Vec::new(),
+                block: None,
+                execution_report: None,
             },
         ]
     }
@@ -137,6 +442,68 @@ mod tests {
         assert!(err.into_serde_err().is_data());
     }
 
+    #[test]
+    fn optional_fields_are_omitted() {
+        let json = r#"[{"schema_version":2,"name":"fft","total_num_cycles":1000,"region_cycles":{},"status":"success"}]"#;
+        let parsed = WorkloadMetrics::from_json(json).expect("deserialize");
+        assert_eq!(parsed[0].error, None);
+        assert_eq!(parsed[0].rejection, None);
+        assert!(parsed[0].transactions.is_empty());
+        assert_eq!(parsed[0].block, None);
+        assert_eq!(parsed[0].execution_report, None);
+
+        let serialized = WorkloadMetrics::to_json(&parsed).expect("serialize");
+        assert_eq!(serialized, json);
+    }
+
+    #[test]
+    fn version_1_entries_are_migrated() {
+        let json = r#"[{"name":"fft","total_num_cycles":1000,"region_cycles":{"compute":800}}]"#;
+        let parsed = WorkloadMetrics::from_json(json).expect("deserialize");
+
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].schema_version, SCHEMA_VERSION);
+        assert_eq!(parsed[0].status, WorkloadStatus::Success);
+        assert_eq!(parsed[0].error, None);
+        assert_eq!(parsed[0].total_num_cycles, 1_000);
+        assert_eq!(parsed[0].region_cycles.flatten()["compute"], 800);
+    }
+
+    #[test]
+    fn newer_schema_versions_are_rejected() {
+        let json = r#"[{"schema_version":99,"name":"fft","total_num_cycles":1000,"region_cycles":{},"status":"success"}]"#;
+        let err = WorkloadMetrics::from_json(json).unwrap_err();
+        assert!(matches!(err, MetricsError::UnsupportedSchemaVersion(99)));
+    }
+
+    #[test]
+    fn cycles_are_normalized_by_gas() {
+        let [fft, aes] = sample().try_into().unwrap();
+
+        assert_eq!(fft.cycles_per_gas(), Some(1_000.0 / 71_000.0));
+        assert_eq!(fft.region_cycles_per_gas()["compute"], 800.0 / 71_000.0);
+        assert_eq!(fft.transactions[0].cycles_per_gas(), Some(300.0 / 21_000.0));
+
+        // Without block properties there is nothing to normalize by.
+        assert_eq!(aes.cycles_per_gas(), None);
+        assert!(aes.region_cycles_per_gas().is_empty());
+    }
+
+    #[test]
+    fn unmeasured_cycles_are_flagged() -> Result<(), MetricsError> {
+        let mut openvm = WorkloadMetrics::for_test("fft", 0, &[]);
+        openvm.cycles_unsupported = true;
+        assert_eq!(openvm.measured_cycles(), None);
+        assert_eq!(openvm.cycles_per_gas(), None);
+        let json = WorkloadMetrics::to_json(std::slice::from_ref(&openvm))?;
+        assert!(json.contains(r#""cycles_unsupported":true"#));
+
+        let succinct = WorkloadMetrics::for_test("fft", 1_000, &[]);
+        assert_eq!(succinct.measured_cycles(), Some(1_000));
+        assert!(!WorkloadMetrics::to_json(&[succinct])?.contains("cycles_unsupported"));
+        Ok(())
+    }
+
     #[test]
     fn file_round_trip() -> Result<(), MetricsError> {
         // Create a named temporary file.
@@ -0,0 +1,315 @@
+//! zkevm-metrics – summarize and compare benchmark results
+//!
+//! Run from the workspace root:
+//!
+//!   cargo run -p zkevm-metrics -- summary zkevm-metrics
+//!   cargo run -p zkevm-metrics -- top zkevm-metrics -n 20 --zkvm succinct
+//!   cargo run -p zkevm-metrics -- report zkevm-metrics --html report.html --markdown report.md
+//!   cargo run -p zkevm-metrics -- export zkevm-metrics metrics.parquet
+//!   cargo run -p zkevm-metrics -- diff baseline-metrics zkevm-metrics --threshold 5
+//!   cargo run -p zkevm-metrics -- savings zkevm-metrics --zkvm succinct
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use zkevm_metrics::{
+    MetricsError, ResultSet, RunManifest,
+    diff::{CyclesChange, DiffThresholds, ResultsDiff},
+    export::{export_rows, write_csv, write_parquet},
+    report::{
+        Report, comparison_table, regions_table, savings_table, summary_table, top_workloads_table,
+    },
+    savings::patch_comparisons,
+    table::Table,
+};
+
+/// Reads a results directory (`<dir>/<zkvm>/<corpus>.json`) and prints tables about it.
+#[derive(Parser)]
+#[command(
+    author,
+    version,
+    about = "Summarize and compare zkEVM benchmark results"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Totals per zkVM.
+    Summary {
+        /// Results directory.
+        dir: PathBuf,
+    },
+    /// The most expensive workloads by total cycles.
+    Top {
+        /// Results directory.
+        dir: PathBuf,
+        /// Number of workloads to show.
+        #[arg(short, default_value_t = 10)]
+        n: usize,
+        /// Only consider the workloads of this zkVM.
+        #[arg(long)]
+        zkvm: Option<String>,
+    },
+    /// Share of the total cycles spent in each region.
+    Regions {
+        /// Results directory.
+        dir: PathBuf,
+        /// Only consider the workloads of this zkVM.
+        #[arg(long)]
+        zkvm: Option<String>,
+    },
+    /// Total cycles of each workload side by side across zkVMs.
+    Compare {
+        /// Results directory.
+        dir: PathBuf,
+    },
+    /// Cycles each workload saves thanks to precompile patches.
+    ///
+    /// Compares the runs of a zkVM built with part of its patch-set to the run with all of it.
+    Savings {
+        /// Results directory.
+        dir: PathBuf,
+        /// Only consider the runs of this zkVM.
+        #[arg(long)]
+        zkvm: Option<String>,
+    },
+    /// Writes a report of the results and of the runs that produced them.
+    ///
+    /// Prints the Markdown report if no output file is given.
+    Report {
+        /// Results directory.
+        dir: PathBuf,
+        /// Write a self-contained HTML page to this file.
+        #[arg(long)]
+        html: Option<PathBuf>,
+        /// Write the Markdown report to this file.
+        #[arg(long)]
+        markdown: Option<PathBuf>,
+        /// Title of the report.
+        #[arg(long, default_value = "zkEVM benchmark report")]
+        title: String,
+    },
+    /// Writes the results as a flat table, with one row per block and per region.
+    Export {
+        /// Results directory.
+        dir: PathBuf,
+        /// File to write.
+        output: PathBuf,
+        /// Format of the file, guessed from the extension of `output` if not given.
+        #[arg(long, value_enum)]
+        format: Option<ExportFormat>,
+    },
+    /// Changes in cycles between two results directories.
+    ///
+    /// Exits with a non-zero status if any workload regressed beyond the thresholds.
+    Diff {
+        /// Baseline results directory.
+        baseline: PathBuf,
+        /// Candidate results directory, compared against the baseline.
+        candidate: PathBuf,
+        /// Maximum increase of the total cycles of a workload, in percent.
+        #[arg(long, default_value_t = 5.0)]
+        threshold: f64,
+        /// Maximum increase of the cycles of a region, in percent. Regions are not checked
+        /// unless this is set.
+        #[arg(long)]
+        region_threshold: Option<f64>,
+        /// Ignore increases of fewer cycles than this.
+        #[arg(long, default_value_t = 0)]
+        min_cycles: u64,
+        /// Do not fail when workloads of the baseline are missing from the candidate.
+        #[arg(long)]
+        allow_missing: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    Csv,
+    Parquet,
+}
+
+fn main() -> Result<ExitCode, MetricsError> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Command::Summary { dir } => print!("{}", summary_table(&load(&dir)?)),
+        Command::Top { dir, n, zkvm } => {
+            print!("{}", top_workloads_table(&load(&dir)?, zkvm.as_deref(), n));
+        }
+        Command::Regions { dir, zkvm } => {
+            print!("{}", regions_table(&load(&dir)?, zkvm.as_deref()));
+        }
+        Command::Compare { dir } => print!("{}", comparison_table(&load(&dir)?)),
+        Command::Savings { dir, zkvm } => {
+            let comparisons =
+                patch_comparisons(&load(&dir)?, &RunManifest::from_results_dir(&dir)?);
+            let comparisons: Vec<_> = comparisons
+                .iter()
+                .filter(|comparison| zkvm.as_ref().is_none_or(|zkvm| comparison.zkvm == *zkvm))
+                .collect();
+            if comparisons.is_empty() {
+                eprintln!("no run with part of a patch-set (see `xtask bench --without`)");
+            }
+            for (index, comparison) in comparisons.into_iter().enumerate() {
+                if index > 0 {
+                    println!();
+                }
+                println!("{} (baseline: {})", comparison.zkvm, comparison.baseline);
+                print!("{}", savings_table(comparison));
+            }
+        }
+        Command::Report {
+            dir,
+            html,
+            markdown,
+            title,
+        } => {
+            let manifests = RunManifest::from_results_dir(&dir)?;
+            let report = Report::new(title, &load(&dir)?, &manifests);
+            if html.is_none() && markdown.is_none() {
+                print!("{}", report.to_markdown());
+            }
+            if let Some(path) = html {
+                fs::write(&path, report.to_html())
+                    .map_err(|err| MetricsError::from(err).in_file(&path))?;
+            }
+            if let Some(path) = markdown {
+                fs::write(&path, report.to_markdown())
+                    .map_err(|err| MetricsError::from(err).in_file(&path))?;
+            }
+        }
+        Command::Export {
+            dir,
+            output,
+            format,
+        } => {
+            let rows = export_rows(&load(&dir)?);
+            let format = format.unwrap_or_else(|| {
+                if output
+                    .extension()
+                    .is_some_and(|extension| extension == "parquet")
+                {
+                    ExportFormat::Parquet
+                } else {
+                    ExportFormat::Csv
+                }
+            });
+            match format {
+                ExportFormat::Csv => write_csv(&rows, &output),
+                ExportFormat::Parquet => write_parquet(&rows, &output),
+            }
+            .map_err(|err| err.in_file(&output))?;
+            eprintln!("wrote {} rows to {}", rows.len(), output.display());
+        }
+        Command::Diff {
+            baseline,
+            candidate,
+            threshold,
+            region_threshold,
+            min_cycles,
+            allow_missing,
+        } => {
+            let thresholds = DiffThresholds {
+                total: threshold / 100.0,
+                region: region_threshold.map(|threshold| threshold / 100.0),
+                min_cycles,
+                allow_missing,
+            };
+            let diff = ResultsDiff::new(&load(&baseline)?, &load(&candidate)?);
+            if !print_diff(&diff, &thresholds) {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn load(dir: &Path) -> Result<ResultSet, MetricsError> {
+    let results = ResultSet::from_dir(dir)?;
+    if results.is_empty() {
+        eprintln!("no metrics found in {}", dir.display());
+    }
+    Ok(results)
+}
+
+/// Prints the changes of every workload and the regressions, and returns whether the candidate
+/// passes the comparison.
+fn print_diff(diff: &ResultsDiff, thresholds: &DiffThresholds) -> bool {
+    let mut workloads: Vec<_> = diff.workloads.iter().collect();
+    workloads.sort_by(|a, b| {
+        let relative = |change: &CyclesChange| change.relative().unwrap_or_default();
+        relative(&b.total).total_cmp(&relative(&a.total))
+    });
+    let mut table = Table::new(["zkVM", "Workload", "Before", "After", "Change", "Change %"])
+        .align_right(&[2, 3, 4, 5]);
+    for workload in workloads {
+        let mut row = vec![workload.zkvm.clone(), workload.name.clone()];
+        row.extend(format_change(&workload.total));
+        table.push_row(row);
+    }
+    print!("{table}");
+
+    for (zkvm, name) in &diff.only_in_baseline {
+        println!("only in baseline: {zkvm} {name}");
+    }
+    for (zkvm, name) in &diff.only_in_candidate {
+        println!("only in candidate: {zkvm} {name}");
+    }
+    for (zkvm, name) in &diff.unmeasured {
+        println!("cycles not measured: {zkvm} {name}");
+    }
+    for (zkvm, name) in &diff.broken {
+        println!("no longer succeeds: {zkvm} {name}");
+    }
+
+    let passes = diff.passes(thresholds);
+    let regressions = diff.regressions(thresholds);
+    if regressions.is_empty() {
+        println!(
+            "\n{}",
+            if passes {
+                "no regressions"
+            } else {
+                "no cycle regressions"
+            }
+        );
+        return passes;
+    }
+    println!("\n{} regression(s):\n", regressions.len());
+    let mut table = Table::new([
+        "zkVM", "Workload", "Region", "Before", "After", "Change", "Change %",
+    ])
+    .align_right(&[3, 4, 5, 6]);
+    for regression in regressions {
+        let mut row = vec![
+            regression.zkvm,
+            regression.name,
+            regression.region.unwrap_or_else(|| "(total)".to_string()),
+        ];
+        row.extend(format_change(&regression.change));
+        table.push_row(row);
+    }
+    print!("{table}");
+    false
+}
+
+/// Formats the before, after, absolute and relative columns of a change.
+fn format_change(change: &CyclesChange) -> [String; 4] {
+    [
+        change.before.to_string(),
+        change.after.to_string(),
+        format!("{:+}", change.absolute()),
+        change.relative().map_or_else(
+            || "-".to_string(),
+            |relative| format!("{:+.2}%", relative * 100.0),
+        ),
+    ]
+}
@@ -0,0 +1,146 @@
+//! Description of a benchmark run, stored next to the metrics it produced.
+
+use serde_derive::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+use crate::MetricsError;
+
+/// Name of the file holding the [`RunManifest`] of a zkVM, in its results directory.
+pub const RUN_MANIFEST_FILE_NAME: &str = "run.manifest.json";
+
+/// Returns the name of the results directory of a run on `zkvm`: the zkVM itself, or
+/// `<zkvm>-<variant>` for a variant of its build (e.g., "succinct-without-sha2").
+#[must_use]
+pub fn results_name(zkvm: &str, variant: Option<&str>) -> String {
+    match variant {
+        Some(variant) => format!("{zkvm}-{variant}"),
+        None => zkvm.to_string(),
+    }
+}
+
+/// How, when and where the results of one zkVM were produced.
+///
+/// The benchmark runner writes it to `<results>/<zkvm>/run.manifest.json` once every corpus has been
+/// executed, or to `<results>/<zkvm>-<variant>/run.manifest.json` for a variant of the zkVM's build.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RunManifest {
+    /// zkVM the workloads ran on (e.g., "succinct").
+    pub zkvm: String,
+    /// Variant of the zkVM's build, e.g., "without-sha2" when the guest was built without the
+    /// `sha2` precompile patch, or `None` for the regular build.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+    /// Precompile patches the guest was built with, by `[patch.crates-io]` key, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patches: Option<Vec<String>>,
+    /// Start of the run, in seconds since the Unix epoch.
+    pub started_at: u64,
+    /// Duration of the run, in seconds.
+    pub duration_secs: u64,
+    /// Commit of the repository the run was built from, if it could be determined.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_commit: Option<String>,
+    /// Operating system of the host (e.g., "linux").
+    pub os: String,
+    /// CPU architecture of the host (e.g., "`x86_64`").
+    pub arch: String,
+    /// Number of CPUs available to the run.
+    pub cpus: usize,
+    /// Names of the corpora that were executed, in order.
+    pub corpora: Vec<String>,
+    /// Number of blocks executed over all corpora.
+    pub blocks: usize,
+}
+
+impl RunManifest {
+    /// Returns the name of the directory holding the results of the run, see [`results_name`].
+    #[must_use]
+    pub fn results_name(&self) -> String {
+        results_name(&self.zkvm, self.variant.as_deref())
+    }
+
+    /// Serializes the manifest using JSON pretty-print and writes it to `path`.
+    ///
+    /// Parent directories are created if they are missing.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MetricsError::Io` if any filesystem operation fails.
+    /// Returns `MetricsError::Serde` if JSON serialization fails.
+    pub fn to_path<P: AsRef<Path>>(&self, path: P) -> Result<(), MetricsError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Reads the manifest at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MetricsError::Io` if reading the file fails.
+    /// Returns `MetricsError::Serde` if JSON deserialization fails.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, MetricsError> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Reads the manifests of every zkVM under the results directory `dir`, ordered by zkVM.
+    ///
+    /// zkVMs without a manifest, e.g., results from before manifests were written, are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MetricsError::File` if `dir` cannot be listed or a manifest cannot be read.
+    pub fn from_results_dir<P: AsRef<Path>>(dir: P) -> Result<Vec<Self>, MetricsError> {
+        let dir = dir.as_ref();
+        let mut paths = fs::read_dir(dir)
+            .and_then(|entries| {
+                entries
+                    .map(|entry| entry.map(|entry| entry.path().join(RUN_MANIFEST_FILE_NAME)))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .map_err(|err| MetricsError::from(err).in_file(dir))?;
+        paths.retain(|path| path.is_file());
+        paths.sort();
+        paths
+            .iter()
+            .map(|path| Self::from_path(path).map_err(|err| err.in_file(path)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifests_are_read_from_results_dir() -> Result<(), MetricsError> {
+        let dir = tempfile::tempdir()?;
+        let manifest = RunManifest {
+            zkvm: "succinct".into(),
+            variant: Some("without-sha2".into()),
+            patches: Some(vec!["k256".into()]),
+            started_at: 1_748_781_000,
+            duration_secs: 3_725,
+            git_commit: Some("abc123".into()),
+            os: "linux".into(),
+            arch: "x86_64".into(),
+            cpus: 16,
+            corpora: vec!["corpus".into()],
+            blocks: 1,
+        };
+        assert_eq!(manifest.results_name(), "succinct-without-sha2");
+        manifest.to_path(
+            dir.path()
+                .join(manifest.results_name())
+                .join(RUN_MANIFEST_FILE_NAME),
+        )?;
+        // zkVMs without a manifest are skipped.
+        fs::create_dir(dir.path().join("risc0"))?;
+
+        assert_eq!(RunManifest::from_results_dir(dir.path())?, [manifest]);
+        Ok(())
+    }
+}
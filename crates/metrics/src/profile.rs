@@ -0,0 +1,88 @@
+//! Opcode and precompile profiles produced by profiling guest builds.
+
+use serde_derive::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+
+use crate::{MetricsError, read_json, write_json};
+
+/// Opcode and precompile profile of a single block.
+///
+/// Stored in a `<corpus>.profile.json` file next to the `WorkloadMetrics` of the corpus, under
+/// the same `name` as the block's `WorkloadMetrics`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BlockProfile {
+    /// Name of the workload, matching `WorkloadMetrics::name`.
+    pub name: String,
+    /// Profile of each opcode that ran, keyed by mnemonic (e.g., "SSTORE").
+    pub opcodes: BTreeMap<String, OperationProfile>,
+    /// Profile of each precompile that was called, keyed by address.
+    pub precompiles: BTreeMap<String, OperationProfile>,
+}
+
+/// Number of executions and cycles of an opcode or precompile.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OperationProfile {
+    /// Number of times the operation ran.
+    pub count: u64,
+    /// Total cycles spent in the operation, over all of its executions.
+    pub cycles: u64,
+}
+
+impl BlockProfile {
+    /// Serializes `items` using JSON pretty-print and writes them to `path`.
+    ///
+    /// Parent directories are created if they are missing.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MetricsError::Io` if any filesystem operation fails.
+    /// Returns `MetricsError::Serde` if JSON serialization fails.
+    pub fn to_path<P: AsRef<Path>>(path: P, items: &[Self]) -> Result<(), MetricsError> {
+        write_json(path.as_ref(), items)
+    }
+
+    /// Reads the file at `path` and deserializes a `Vec<BlockProfile>` from its JSON content.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MetricsError::Io` if reading the file fails.
+    /// Returns `MetricsError::Serde` if JSON deserialization fails.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Vec<Self>, MetricsError> {
+        read_json(path.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn profile_file_round_trip() -> Result<(), MetricsError> {
+        let temp_file = NamedTempFile::new()?;
+        let profiles = vec![BlockProfile {
+            name: "fft".into(),
+            opcodes: [(
+                "SSTORE".to_string(),
+                OperationProfile {
+                    count: 2,
+                    cycles: 4_000,
+                },
+            )]
+            .into(),
+            precompiles: [(
+                "0x0000000000000000000000000000000000000005".to_string(),
+                OperationProfile {
+                    count: 1,
+                    cycles: 90_000,
+                },
+            )]
+            .into(),
+        }];
+
+        BlockProfile::to_path(temp_file.path(), &profiles)?;
+        assert_eq!(BlockProfile::from_path(temp_file.path())?, profiles);
+
+        Ok(())
+    }
+}
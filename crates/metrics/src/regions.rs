@@ -0,0 +1,243 @@
+//! Hierarchical view of the cycles spent in named regions.
+//!
+//! Guests name nested regions after their path, separated by `/` (e.g.,
+//! `validation/execution`), and report the cycles of each region inclusive of the regions nested
+//! in it. [`RegionTree`] rebuilds the nesting from these names, so that the cycles of a region can
+//! be split into the part spent in its children and the part spent in the region itself.
+//!
+//! A `RegionTree` serializes as the flat map of region paths to inclusive cycles, so metrics
+//! files keep the same format as before regions were hierarchical.
+
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Separator between the segments of a region path.
+pub const REGION_PATH_SEPARATOR: char = '/';
+
+/// Tree of regions, built from the flat map of region paths to inclusive cycles.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "HashMap<String, u64>", into = "HashMap<String, u64>")]
+pub struct RegionTree {
+    roots: BTreeMap<String, RegionNode>,
+}
+
+/// A region and the regions nested in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegionNode {
+    path: String,
+    cycles: Option<u64>,
+    children: BTreeMap<String, Self>,
+}
+
+impl RegionTree {
+    /// Returns `true` if no region was recorded.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// Returns the top-level regions, ordered by name.
+    pub fn roots(&self) -> impl Iterator<Item = &RegionNode> {
+        self.roots.values()
+    }
+
+    /// Returns the region at `path` (e.g., `validation/execution`), if it exists.
+    #[must_use]
+    pub fn get(&self, path: &str) -> Option<&RegionNode> {
+        let mut segments = path.split(REGION_PATH_SEPARATOR);
+        let mut node = self.roots.get(segments.next()?)?;
+        for segment in segments {
+            node = node.children.get(segment)?;
+        }
+        Some(node)
+    }
+
+    /// Returns every region of the tree, parents before their children.
+    pub fn iter(&self) -> impl Iterator<Item = &RegionNode> {
+        let mut stack: Vec<&RegionNode> = self.roots.values().rev().collect();
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.children.values().rev());
+            Some(node)
+        })
+    }
+
+    /// Returns the flat map of region paths to inclusive cycles the tree was built from.
+    ///
+    /// Regions that were only implied by the path of a nested region are left out.
+    #[must_use]
+    pub fn flatten(&self) -> HashMap<String, u64> {
+        self.iter()
+            .filter_map(|node| Some((node.path.clone(), node.cycles?)))
+            .collect()
+    }
+
+    /// Returns, for every recorded region with nested regions, the cycles that are not
+    /// attributed to any of them.
+    #[must_use]
+    pub fn unaccounted(&self) -> BTreeMap<String, u64> {
+        self.iter()
+            .filter(|node| node.cycles.is_some() && !node.children.is_empty())
+            .map(|node| (node.path.clone(), node.self_cycles()))
+            .collect()
+    }
+
+    /// Adds the region at `path` with the given inclusive `cycles`.
+    ///
+    /// Cycles recorded twice for the same path are summed.
+    pub fn insert(&mut self, path: &str, cycles: u64) {
+        let mut segments = path.split(REGION_PATH_SEPARATOR);
+        let Some(root) = segments.next() else {
+            return;
+        };
+        let mut node = self
+            .roots
+            .entry(root.to_string())
+            .or_insert_with(|| RegionNode::new(root.to_string()));
+        for segment in segments {
+            let child_path = format!("{}{REGION_PATH_SEPARATOR}{segment}", node.path);
+            node = node
+                .children
+                .entry(segment.to_string())
+                .or_insert_with(|| RegionNode::new(child_path));
+        }
+        *node.cycles.get_or_insert(0) += cycles;
+    }
+}
+
+impl RegionNode {
+    const fn new(path: String) -> Self {
+        Self {
+            path,
+            cycles: None,
+            children: BTreeMap::new(),
+        }
+    }
+
+    /// Full path of the region (e.g., `validation/execution`).
+    #[must_use]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Last segment of the region path (e.g., `execution`).
+    #[must_use]
+    pub fn name(&self) -> &str {
+        self.path
+            .rsplit(REGION_PATH_SEPARATOR)
+            .next()
+            .unwrap_or(&self.path)
+    }
+
+    /// Whether cycles were recorded for this region, rather than it only being implied by the
+    /// path of a nested region.
+    #[must_use]
+    pub const fn is_recorded(&self) -> bool {
+        self.cycles.is_some()
+    }
+
+    /// Cycles spent in the region, including the regions nested in it.
+    ///
+    /// For a region that was not recorded, this is the sum of its children.
+    #[must_use]
+    pub fn inclusive_cycles(&self) -> u64 {
+        self.cycles.unwrap_or_else(|| self.children_cycles())
+    }
+
+    /// Cycles spent in the region itself, outside of any region nested in it.
+    #[must_use]
+    pub fn self_cycles(&self) -> u64 {
+        self.inclusive_cycles()
+            .saturating_sub(self.children_cycles())
+    }
+
+    /// Returns the regions nested directly in this one, ordered by name.
+    pub fn children(&self) -> impl Iterator<Item = &Self> {
+        self.children.values()
+    }
+
+    fn children_cycles(&self) -> u64 {
+        self.children.values().map(Self::inclusive_cycles).sum()
+    }
+}
+
+impl FromIterator<(String, u64)> for RegionTree {
+    fn from_iter<I: IntoIterator<Item = (String, u64)>>(iter: I) -> Self {
+        let mut tree = Self::default();
+        for (path, cycles) in iter {
+            tree.insert(&path, cycles);
+        }
+        tree
+    }
+}
+
+impl From<HashMap<String, u64>> for RegionTree {
+    fn from(region_cycles: HashMap<String, u64>) -> Self {
+        region_cycles.into_iter().collect()
+    }
+}
+
+impl From<RegionTree> for HashMap<String, u64> {
+    fn from(tree: RegionTree) -> Self {
+        tree.flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regions_are_nested_by_path() {
+        let tree = RegionTree::from_iter([
+            ("validation".to_string(), 100),
+            ("validation/execution".to_string(), 60),
+            ("validation/execution/evm".to_string(), 50),
+            ("validation/state_root".to_string(), 30),
+            ("profile/opcode/ADD".to_string(), 5),
+        ]);
+
+        let validation = tree.get("validation").unwrap();
+        assert_eq!(validation.inclusive_cycles(), 100);
+        assert_eq!(validation.self_cycles(), 10);
+        let execution = tree.get("validation/execution").unwrap();
+        assert_eq!(execution.name(), "execution");
+        assert_eq!(execution.self_cycles(), 10);
+        assert_eq!(
+            tree.get("validation/execution/evm").unwrap().self_cycles(),
+            50
+        );
+
+        // Parents that are only implied by a nested path add up their children.
+        let profile = tree.get("profile").unwrap();
+        assert!(!profile.is_recorded());
+        assert_eq!(profile.inclusive_cycles(), 5);
+        assert_eq!(profile.self_cycles(), 0);
+
+        assert_eq!(
+            tree.unaccounted(),
+            BTreeMap::from([
+                ("validation".to_string(), 10),
+                ("validation/execution".to_string(), 10),
+            ])
+        );
+    }
+
+    #[test]
+    fn region_tree_serializes_as_flat_map() {
+        let flat = HashMap::from([
+            ("validation".to_string(), 100),
+            ("validation/execution".to_string(), 60),
+            ("profile/opcode/ADD".to_string(), 5),
+        ]);
+        let tree = RegionTree::from(flat.clone());
+        assert_eq!(tree.flatten(), flat);
+
+        let json = serde_json::to_string(&tree).unwrap();
+        assert_eq!(
+            serde_json::from_str::<HashMap<String, u64>>(&json).unwrap(),
+            flat
+        );
+        assert_eq!(serde_json::from_str::<RegionTree>(&json).unwrap(), tree);
+    }
+}
@@ -0,0 +1,522 @@
+//! Shareable reports of a results directory, as Markdown or as a self-contained HTML page.
+//!
+//! A [`Report`] gathers the run manifests, the totals per zkVM, a ranking of the most expensive
+//! workloads, a breakdown of the cycles per region for each zkVM, a comparison of the
+//! workloads across zkVMs and, when zkVMs also ran with part of their patch-set, the cycles
+//! each precompile patch saves. The HTML page inlines its styles and draws bar charts with plain
+//! elements, so it can be opened offline and sent as a single file.
+//!
+//! The tables are also used by the metrics CLI.
+
+use std::fmt::Write;
+
+use crate::{
+    ResultSet, RunManifest, WorkloadStatus,
+    savings::{PatchComparison, patch_comparisons},
+    summary::{compare_zkvms, region_shares, top_workloads, zkvm_summaries},
+    table::{Table, escape_html},
+};
+
+/// Number of workloads ranked in a report.
+pub const REPORT_TOP_WORKLOADS: usize = 20;
+
+/// Shown in place of cycle counts the zkVM did not report.
+const UNMEASURED: &str = "n/a";
+
+/// Width of the region bars in a Markdown report, in characters.
+const MARKDOWN_BAR_WIDTH: f64 = 30.0;
+
+/// Styles of the HTML report.
+const HTML_STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 2rem auto; max-width: 72rem; color: #222; }
+h1, h2, h3 { font-weight: 600; }
+table { border-collapse: collapse; margin: 1rem 0; font-size: 0.9rem; }
+th, td { border: 1px solid #ccc; padding: 0.25rem 0.6rem; text-align: left; }
+th { background: #f2f2f2; }
+.num { text-align: right; font-variant-numeric: tabular-nums; }
+.bars { margin: 1rem 0; font-size: 0.9rem; }
+.bar { display: flex; align-items: center; gap: 0.6rem; margin: 0.15rem 0; }
+.bar .label { flex: 0 0 22rem; overflow: hidden; text-overflow: ellipsis; white-space: nowrap; }
+.bar .track { flex: 1; background: #eee; height: 0.9rem; }
+.bar .fill { display: block; background: #4c78a8; height: 100%; }
+.bar .num { flex: 0 0 5rem; }
+";
+
+/// A report of a results directory.
+#[derive(Debug, Clone)]
+pub struct Report {
+    title: String,
+    sections: Vec<Section>,
+}
+
+#[derive(Debug, Clone)]
+struct Section {
+    title: String,
+    blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone)]
+enum Block {
+    Heading(String),
+    Table(Table),
+    Bars(Vec<Bar>),
+}
+
+/// A bar of a bar chart: the share of the total cycles spent in a region.
+#[derive(Debug, Clone)]
+struct Bar {
+    label: String,
+    share: f64,
+}
+
+impl Report {
+    /// Builds the report of `results`, described by the `manifests` of the runs that produced
+    /// them.
+    #[must_use]
+    pub fn new(title: impl Into<String>, results: &ResultSet, manifests: &[RunManifest]) -> Self {
+        let mut sections = Vec::new();
+        if !manifests.is_empty() {
+            sections.push(Section {
+                title: "Runs".to_string(),
+                blocks: vec![Block::Table(runs_table(manifests))],
+            });
+        }
+        sections.push(Section {
+            title: "Totals per zkVM".to_string(),
+            blocks: vec![Block::Table(summary_table(results))],
+        });
+        sections.push(Section {
+            title: "Most expensive workloads".to_string(),
+            blocks: vec![Block::Table(top_workloads_table(
+                results,
+                None,
+                REPORT_TOP_WORKLOADS,
+            ))],
+        });
+
+        let mut breakdown = Vec::new();
+        for zkvm in results.zkvms() {
+            let bars = region_shares(results, Some(zkvm))
+                .into_iter()
+                .map(|region| Bar {
+                    label: region.path,
+                    share: region.share,
+                })
+                .collect::<Vec<_>>();
+            if !bars.is_empty() {
+                breakdown.push(Block::Heading(zkvm.to_string()));
+                breakdown.push(Block::Bars(bars));
+            }
+        }
+        if !breakdown.is_empty() {
+            sections.push(Section {
+                title: "Share of cycles per region".to_string(),
+                blocks: breakdown,
+            });
+        }
+
+        if results.zkvms().len() > 1 {
+            sections.push(Section {
+                title: "Comparison across zkVMs".to_string(),
+                blocks: vec![Block::Table(comparison_table(results))],
+            });
+        }
+
+        let savings = patch_comparisons(results, manifests)
+            .iter()
+            .flat_map(|comparison| {
+                [
+                    Block::Heading(format!(
+                        "{} (baseline: {})",
+                        comparison.zkvm, comparison.baseline
+                    )),
+                    Block::Table(savings_table(comparison)),
+                ]
+            })
+            .collect::<Vec<_>>();
+        if !savings.is_empty() {
+            sections.push(Section {
+                title: "Cycles saved by precompile patches".to_string(),
+                blocks: savings,
+            });
+        }
+
+        Self {
+            title: title.into(),
+            sections,
+        }
+    }
+
+    /// Renders the report as Markdown.
+    #[must_use]
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = format!("# {}\n", self.title);
+        for section in &self.sections {
+            let _ = write!(markdown, "\n## {}\n", section.title);
+            for block in &section.blocks {
+                markdown.push('\n');
+                match block {
+                    Block::Heading(heading) => {
+                        let _ = writeln!(markdown, "### {heading}");
+                    }
+                    Block::Table(table) => markdown.push_str(&table.to_string()),
+                    Block::Bars(bars) => {
+                        let mut table = Table::new(["Region", "Share", ""]).align_right(&[1]);
+                        for bar in bars {
+                            let width = (bar.share * MARKDOWN_BAR_WIDTH).round() as usize;
+                            table.push_row([
+                                bar.label.clone(),
+                                format_share(bar.share),
+                                "█".repeat(width),
+                            ]);
+                        }
+                        markdown.push_str(&table.to_string());
+                    }
+                }
+            }
+        }
+        markdown
+    }
+
+    /// Renders the report as a self-contained HTML page.
+    #[must_use]
+    pub fn to_html(&self) -> String {
+        let title = escape_html(&self.title);
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{title}</title>\n<style>{HTML_STYLE}</style>\n</head>\n<body>\n\
+             <h1>{title}</h1>\n"
+        );
+        for section in &self.sections {
+            let _ = writeln!(html, "<h2>{}</h2>", escape_html(&section.title));
+            for block in &section.blocks {
+                match block {
+                    Block::Heading(heading) => {
+                        let _ = writeln!(html, "<h3>{}</h3>", escape_html(heading));
+                    }
+                    Block::Table(table) => html.push_str(&table.to_html()),
+                    Block::Bars(bars) => {
+                        html.push_str("<div class=\"bars\">\n");
+                        for bar in bars {
+                            let label = escape_html(&bar.label);
+                            let _ = writeln!(
+                                html,
+                                "<div class=\"bar\"><span class=\"label\" title=\"{label}\">\
+                                 {label}</span><span class=\"track\"><span class=\"fill\" \
+                                 style=\"width: {:.2}%\"></span></span>\
+                                 <span class=\"num\">{}</span></div>",
+                                bar.share * 100.0,
+                                format_share(bar.share),
+                            );
+                        }
+                        html.push_str("</div>\n");
+                    }
+                }
+            }
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+/// Tabulates the runs that produced the results.
+#[must_use]
+pub fn runs_table(manifests: &[RunManifest]) -> Table {
+    let mut table = Table::new([
+        "zkVM",
+        "Started (UTC)",
+        "Duration",
+        "Commit",
+        "Host",
+        "Corpora",
+        "Blocks",
+    ])
+    .align_right(&[2, 5, 6]);
+    for manifest in manifests {
+        table.push_row([
+            manifest.zkvm.clone(),
+            format_timestamp(manifest.started_at),
+            format_duration(manifest.duration_secs),
+            manifest.git_commit.clone().unwrap_or_default(),
+            format!("{}-{}, {} CPUs", manifest.arch, manifest.os, manifest.cpus),
+            manifest.corpora.len().to_string(),
+            manifest.blocks.to_string(),
+        ]);
+    }
+    table
+}
+
+/// Tabulates the totals of each zkVM.
+#[must_use]
+pub fn summary_table(results: &ResultSet) -> Table {
+    let mut table = Table::new([
+        "zkVM",
+        "Workloads",
+        "Failures",
+        "Total cycles",
+        "Mean cycles",
+        "Cycles/gas",
+        "Most expensive",
+    ])
+    .align_right(&[1, 2, 3, 4, 5]);
+    for summary in zkvm_summaries(results) {
+        table.push_row([
+            summary.zkvm.clone(),
+            summary.workloads.to_string(),
+            summary.failures.to_string(),
+            if summary.unmeasured == summary.workloads {
+                UNMEASURED.to_string()
+            } else {
+                summary.total_cycles.to_string()
+            },
+            summary
+                .mean_cycles()
+                .map_or_else(|| UNMEASURED.to_string(), |mean| mean.to_string()),
+            format_optional(summary.cycles_per_gas()),
+            summary
+                .most_expensive
+                .map(|(name, cycles)| format!("{name} ({cycles})"))
+                .unwrap_or_default(),
+        ]);
+    }
+    table
+}
+
+/// Ranks the `n` workloads with the most total cycles, on `zkvm` or on any zkVM.
+#[must_use]
+pub fn top_workloads_table(results: &ResultSet, zkvm: Option<&str>, n: usize) -> Table {
+    let mut table = Table::new([
+        "#",
+        "zkVM",
+        "Workload",
+        "Total cycles",
+        "Gas used",
+        "Cycles/gas",
+        "Status",
+    ])
+    .align_right(&[0, 3, 4, 5]);
+    for (rank, result) in top_workloads(results, zkvm, n).into_iter().enumerate() {
+        let metrics = &result.metrics;
+        table.push_row([
+            (rank + 1).to_string(),
+            result.zkvm.clone(),
+            metrics.name.clone(),
+            metrics.total_num_cycles.to_string(),
+            metrics
+                .block
+                .map(|block| block.gas_used.to_string())
+                .unwrap_or_default(),
+            format_optional(metrics.cycles_per_gas()),
+            match metrics.status {
+                WorkloadStatus::Success => "success".to_string(),
+                WorkloadStatus::CorrectnessFailure => "correctness failure".to_string(),
+            },
+        ]);
+    }
+    table
+}
+
+/// Tabulates the share of the total cycles spent in each region, on `zkvm` or on all zkVMs.
+#[must_use]
+pub fn regions_table(results: &ResultSet, zkvm: Option<&str>) -> Table {
+    let mut table =
+        Table::new(["Region", "Cycles", "Self cycles", "Share"]).align_right(&[1, 2, 3]);
+    for region in region_shares(results, zkvm) {
+        table.push_row([
+            format!("{}{}", "  ".repeat(region.depth), region.path),
+            region.cycles.to_string(),
+            region.self_cycles.to_string(),
+            format_share(region.share),
+        ]);
+    }
+    table
+}
+
+/// Lines up the total cycles of each workload across zkVMs, relative to the cheapest one.
+#[must_use]
+pub fn comparison_table(results: &ResultSet) -> Table {
+    let zkvms = results.zkvms();
+    let mut table = Table::new(
+        std::iter::once("Workload".to_string())
+            .chain(zkvms.iter().map(|zkvm| format!("{zkvm} cycles")))
+            .chain(zkvms.iter().map(|zkvm| format!("{zkvm} vs cheapest"))),
+    )
+    .align_right(&(1..=2 * zkvms.len()).collect::<Vec<_>>());
+    for comparison in compare_zkvms(results) {
+        let cycles = zkvms.iter().map(|zkvm| {
+            comparison
+                .cycles
+                .get(*zkvm)
+                .map(u64::to_string)
+                .unwrap_or_else(|| "-".to_string())
+        });
+        let relative = zkvms.iter().map(|zkvm| {
+            comparison
+                .relative_to_cheapest(zkvm)
+                .map_or_else(|| "-".to_string(), |ratio| format!("{ratio:.2}x"))
+        });
+        table.push_row(
+            std::iter::once(comparison.name.clone())
+                .chain(cycles)
+                .chain(relative),
+        );
+    }
+    table
+}
+
+/// Tabulates the cycles each workload saves thanks to the patches left out by each variant of
+/// `comparison`, with the totals over all workloads in the last row.
+#[must_use]
+pub fn savings_table(comparison: &PatchComparison) -> Table {
+    let variants = &comparison.variants;
+    let mut table = Table::new(
+        ["Workload".to_string(), "Cycles".to_string()]
+            .into_iter()
+            .chain(
+                variants
+                    .iter()
+                    .map(|variant| format!("Saved by {}", variant.label())),
+            ),
+    )
+    .align_right(&(1..=1 + variants.len()).collect::<Vec<_>>());
+
+    let format_saving = |saved: Option<i128>, cycles: u64| {
+        saved.map_or_else(
+            || "-".to_string(),
+            |saved| {
+                let without = i128::from(cycles) + saved;
+                if without == 0 {
+                    saved.to_string()
+                } else {
+                    format!("{saved} ({})", format_share(saved as f64 / without as f64))
+                }
+            },
+        )
+    };
+    for workload in &comparison.workloads {
+        table.push_row(
+            [workload.name.clone(), workload.baseline_cycles.to_string()]
+                .into_iter()
+                .chain(
+                    workload
+                        .savings
+                        .iter()
+                        .map(|saved| format_saving(*saved, workload.baseline_cycles)),
+                ),
+        );
+    }
+
+    // Totals over the workloads that ran in every variant, so that they compare.
+    let complete = comparison
+        .workloads
+        .iter()
+        .filter(|workload| workload.savings.iter().all(Option::is_some));
+    let cycles: u64 = complete
+        .clone()
+        .map(|workload| workload.baseline_cycles)
+        .sum();
+    table.push_row(["Total".to_string(), cycles.to_string()].into_iter().chain(
+        (0..variants.len()).map(|index| {
+            let saved = complete
+                .clone()
+                .filter_map(|workload| workload.savings[index])
+                .sum();
+            format_saving(Some(saved), cycles)
+        }),
+    ));
+    table
+}
+
+fn format_optional(value: Option<f64>) -> String {
+    value.map(|value| format!("{value:.2}")).unwrap_or_default()
+}
+
+fn format_share(share: f64) -> String {
+    format!("{:.2}%", share * 100.0)
+}
+
+fn format_duration(secs: u64) -> String {
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, s) => format!("{m}m {s}s"),
+        (h, m, s) => format!("{h}h {m}m {s}s"),
+    }
+}
+
+/// Formats seconds since the Unix epoch as a UTC date and time (e.g., "2025-06-01 12:30").
+fn format_timestamp(secs: u64) -> String {
+    let days = secs / 86_400;
+    let (hour, minute) = (secs / 3600 % 24, secs / 60 % 60);
+
+    // Converts days since the epoch to a proleptic Gregorian date, after
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02} {hour:02}:{minute:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WorkloadMetrics;
+
+    #[test]
+    fn report_includes_runs_and_regions() {
+        let results = ResultSet::for_test([
+            ("risc0", WorkloadMetrics::for_test("fft", 2_000, &[])),
+            (
+                "succinct",
+                WorkloadMetrics::for_test("fft", 1_000, &[("compute", 800)]),
+            ),
+        ]);
+        let manifest = RunManifest {
+            zkvm: "succinct".into(),
+            variant: None,
+            patches: None,
+            started_at: 1_748_781_000,
+            duration_secs: 3_725,
+            git_commit: Some("abc123".into()),
+            os: "linux".into(),
+            arch: "x86_64".into(),
+            cpus: 16,
+            corpora: vec!["corpus".into()],
+            blocks: 1,
+        };
+
+        let report = Report::new("<Nightly>", &results, &[manifest]);
+        let markdown = report.to_markdown();
+        assert!(markdown.starts_with("# <Nightly>\n"));
+        assert!(markdown.contains("| succinct | 2025-06-01 12:30 | 1h 2m 5s | abc123 |"));
+        assert!(markdown.contains("## Comparison across zkVMs"));
+        assert!(markdown.contains(&format!("| 80.00% | {} |", "█".repeat(24))));
+
+        let html = report.to_html();
+        assert!(html.contains("<h1>&lt;Nightly&gt;</h1>"));
+        assert!(html.contains("style=\"width: 80.00%\""));
+        assert!(!html.contains("<script") && !html.contains("http"));
+    }
+
+    #[test]
+    fn unmeasured_cycles_are_not_shown_as_zero() {
+        let mut unmeasured = WorkloadMetrics::for_test("fft", 0, &[]);
+        unmeasured.cycles_unsupported = true;
+        let results = ResultSet::for_test([("openvm", unmeasured)]);
+
+        // Neither the total nor the mean cycles were measured.
+        let table = summary_table(&results).to_string();
+        assert_eq!(table.matches(UNMEASURED).count(), 2, "{table}");
+    }
+}
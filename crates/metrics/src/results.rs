@@ -0,0 +1,179 @@
+//! Loading a whole results directory.
+//!
+//! Hosts write the metrics of each corpus to `<results>/<zkvm>/<corpus>.json`. A [`ResultSet`]
+//! reads all of them back, remembering which zkVM and corpus each workload belongs to.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{MetricsError, RUN_MANIFEST_FILE_NAME, WorkloadMetrics};
+
+/// Suffix of the files holding `BlockProfile`s, which live next to the metrics files.
+pub const PROFILE_FILE_SUFFIX: &str = ".profile.json";
+
+/// Metrics of a single workload, with where they were recorded.
+#[derive(Debug, PartialEq, Eq)]
+pub struct WorkloadResult {
+    /// zkVM the workload ran on (the name of its directory, e.g., "succinct").
+    pub zkvm: String,
+    /// Corpus the workload belongs to (the name of its file, without extension).
+    pub corpus: String,
+    /// Metrics recorded for the workload.
+    pub metrics: WorkloadMetrics,
+}
+
+/// All the workload metrics of a results directory.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ResultSet {
+    /// Workloads ordered by zkVM, corpus, then position in the corpus file.
+    pub results: Vec<WorkloadResult>,
+}
+
+impl ResultSet {
+    /// Loads every `<zkvm>/<corpus>.json` metrics file under `dir`.
+    ///
+    /// Files that are not metrics files (e.g., profiles and run manifests) are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MetricsError::File` if a directory cannot be listed or a metrics file cannot
+    /// be read.
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Result<Self, MetricsError> {
+        let mut results = Vec::new();
+        for zkvm_dir in sorted_entries(dir.as_ref())? {
+            if !zkvm_dir.is_dir() {
+                continue;
+            }
+            let zkvm = file_name(&zkvm_dir);
+            for file in sorted_entries(&zkvm_dir)? {
+                let Some(corpus) = metrics_file_corpus(&file) else {
+                    continue;
+                };
+                let metrics =
+                    WorkloadMetrics::from_path(&file).map_err(|err| err.in_file(&file))?;
+                results.extend(metrics.into_iter().map(|metrics| WorkloadResult {
+                    zkvm: zkvm.clone(),
+                    corpus: corpus.clone(),
+                    metrics,
+                }));
+            }
+        }
+        Ok(Self { results })
+    }
+
+    /// Returns `true` if the results directory held no workload.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    /// Returns the names of the zkVMs with results, in order.
+    #[must_use]
+    pub fn zkvms(&self) -> Vec<&str> {
+        let mut zkvms: Vec<&str> = self.results.iter().map(|r| r.zkvm.as_str()).collect();
+        zkvms.dedup();
+        zkvms
+    }
+
+    /// Returns the workloads that ran on `zkvm`, or all workloads if `zkvm` is `None`.
+    pub fn filter_zkvm<'a>(
+        &'a self,
+        zkvm: Option<&'a str>,
+    ) -> impl Iterator<Item = &'a WorkloadResult> {
+        self.results
+            .iter()
+            .filter(move |result| zkvm.is_none_or(|zkvm| result.zkvm == zkvm))
+    }
+
+    /// Returns the workload `name` that ran on `zkvm`, if any.
+    #[must_use]
+    pub fn get(&self, zkvm: &str, name: &str) -> Option<&WorkloadResult> {
+        self.results
+            .iter()
+            .find(|result| result.zkvm == zkvm && result.metrics.name == name)
+    }
+}
+
+/// Returns the corpus name of the metrics file at `path`, or `None` if it is not a metrics file.
+fn metrics_file_corpus(path: &Path) -> Option<String> {
+    let file_name = path.file_name()?.to_str()?;
+    if !path.is_file()
+        || file_name.ends_with(PROFILE_FILE_SUFFIX)
+        || file_name == RUN_MANIFEST_FILE_NAME
+    {
+        return None;
+    }
+    file_name.strip_suffix(".json").map(str::to_string)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Lists the entries of `dir` in lexicographic order, so results load deterministically.
+fn sorted_entries(dir: &Path) -> Result<Vec<PathBuf>, MetricsError> {
+    let read_dir = fs::read_dir(dir).map_err(|err| MetricsError::from(err).in_file(dir))?;
+    let mut entries = read_dir
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| MetricsError::from(err).in_file(dir))?;
+    entries.sort();
+    Ok(entries)
+}
+
+#[cfg(test)]
+impl ResultSet {
+    /// Results of `workloads`, given as `(zkvm, metrics)`, all in the corpus `corpus`.
+    pub(crate) fn for_test<'a>(
+        workloads: impl IntoIterator<Item = (&'a str, WorkloadMetrics)>,
+    ) -> Self {
+        Self {
+            results: workloads
+                .into_iter()
+                .map(|(zkvm, metrics)| WorkloadResult {
+                    zkvm: zkvm.into(),
+                    corpus: "corpus".into(),
+                    metrics,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_directory_is_loaded() -> Result<(), MetricsError> {
+        let dir = tempfile::tempdir()?;
+        let fft = WorkloadMetrics::for_test("fft", 1_000, &[]);
+        let aes = WorkloadMetrics::for_test("aes", 2_000, &[]);
+        let sha = WorkloadMetrics::for_test("sha", 500, &[]);
+        WorkloadMetrics::to_path(dir.path().join("succinct/corpus.json"), &[fft])?;
+        WorkloadMetrics::to_path(dir.path().join("risc0/corpus.json"), &[aes])?;
+        // A corpus may be named like the manifest without its suffix.
+        WorkloadMetrics::to_path(dir.path().join("risc0/run.json"), &[sha])?;
+        // Profiles and run manifests next to the metrics are not metrics files.
+        fs::write(dir.path().join("risc0/corpus.profile.json"), "[]")?;
+        fs::write(dir.path().join("risc0").join(RUN_MANIFEST_FILE_NAME), "{}")?;
+
+        let results = ResultSet::from_dir(dir.path())?;
+        assert_eq!(results.zkvms(), ["risc0", "succinct"]);
+        assert_eq!(results.results.len(), 3);
+        assert_eq!(results.get("succinct", "fft").unwrap().corpus, "corpus");
+        assert_eq!(results.get("risc0", "sha").unwrap().corpus, "run");
+        assert_eq!(results.filter_zkvm(Some("risc0")).count(), 2);
+
+        fs::write(dir.path().join("risc0/broken.json"), "{")?;
+        let err = ResultSet::from_dir(dir.path()).unwrap_err();
+        assert!(
+            matches!(&err, MetricsError::File { path, .. } if path.ends_with("risc0/broken.json"))
+        );
+        Ok(())
+    }
+}
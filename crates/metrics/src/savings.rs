@@ -0,0 +1,226 @@
+//! Cycles saved by precompile patches.
+//!
+//! A zkVM can be benchmarked with only part of its patch-set (see `xtask bench --without`), in
+//! which case the results of the run go to a separate directory and its [`RunManifest`] records
+//! the patches it was built with. Comparing such a variant with the run using the whole
+//! patch-set attributes the difference in cycles of each workload to the patches the variant
+//! left out.
+
+use std::collections::BTreeMap;
+
+use crate::{ResultSet, RunManifest};
+
+/// The runs of a zkVM built with different subsets of its patch-set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchComparison {
+    /// zkVM the runs are for (e.g., "succinct").
+    pub zkvm: String,
+    /// Name of the results of the baseline, the run with the most patches.
+    pub baseline: String,
+    /// Patches of the baseline.
+    pub patches: Vec<String>,
+    /// Runs that left out some of the baseline's patches, ordered by name.
+    pub variants: Vec<PatchVariant>,
+    /// Savings of each workload of the baseline, in its order.
+    pub workloads: Vec<WorkloadSavings>,
+}
+
+/// A run with part of the baseline's patches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchVariant {
+    /// Name of the results of the run (e.g., "succinct-without-sha2").
+    pub results: String,
+    /// Patches of the baseline the run was built without.
+    pub removed: Vec<String>,
+    /// Whether the run was built without any patch.
+    pub unpatched: bool,
+}
+
+impl PatchVariant {
+    /// Returns what the variant's savings are attributed to: the removed patches, or "all
+    /// patches".
+    #[must_use]
+    pub fn label(&self) -> String {
+        if self.unpatched {
+            "all patches".to_string()
+        } else {
+            self.removed.join(" + ")
+        }
+    }
+}
+
+/// Cycles a workload saves thanks to the patches each variant left out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkloadSavings {
+    /// Name of the workload.
+    pub name: String,
+    /// Total cycles of the workload in the baseline.
+    pub baseline_cycles: u64,
+    /// Cycles saved, for each variant: the variant's total cycles minus the baseline's, or
+    /// `None` if the workload did not run in the variant or its cycles were not measured.
+    /// Negative if the patches cost cycles.
+    pub savings: Vec<Option<i128>>,
+}
+
+impl WorkloadSavings {
+    /// Returns the share of the cycles of the workload without the patches of variant `index`
+    /// that they save.
+    #[must_use]
+    pub fn relative(&self, index: usize) -> Option<f64> {
+        let saved = self.savings[index]?;
+        let without = i128::from(self.baseline_cycles) + saved;
+        (without != 0).then(|| saved as f64 / without as f64)
+    }
+}
+
+/// Pairs, for each zkVM, the run with the most patches with the runs that left some of them
+/// out, and computes the cycles each workload saves thanks to the patches.
+///
+/// Only runs whose manifest records their patches are considered. zkVMs without any such
+/// variant are left out, as are workloads whose cycles the zkVM did not report.
+#[must_use]
+pub fn patch_comparisons(results: &ResultSet, manifests: &[RunManifest]) -> Vec<PatchComparison> {
+    let mut by_zkvm: BTreeMap<&str, Vec<(&RunManifest, &Vec<String>)>> = BTreeMap::new();
+    for manifest in manifests {
+        if let Some(patches) = &manifest.patches {
+            by_zkvm
+                .entry(manifest.zkvm.as_str())
+                .or_default()
+                .push((manifest, patches));
+        }
+    }
+
+    let mut comparisons = Vec::new();
+    for (zkvm, runs) in by_zkvm {
+        // The regular build wins over a variant with as many patches.
+        let Some(&(baseline, patches)) = runs
+            .iter()
+            .max_by_key(|(manifest, patches)| (patches.len(), manifest.variant.is_none()))
+        else {
+            continue;
+        };
+        let mut variants: Vec<PatchVariant> = runs
+            .iter()
+            .filter(|(_, variant)| {
+                variant.len() < patches.len() && variant.iter().all(|key| patches.contains(key))
+            })
+            .map(|(manifest, variant)| PatchVariant {
+                results: manifest.results_name(),
+                removed: patches
+                    .iter()
+                    .filter(|key| !variant.contains(key))
+                    .cloned()
+                    .collect(),
+                unpatched: variant.is_empty(),
+            })
+            .collect();
+        if variants.is_empty() {
+            continue;
+        }
+        variants.sort_by(|a, b| a.results.cmp(&b.results));
+
+        let baseline_name = baseline.results_name();
+        let workloads = results
+            .filter_zkvm(Some(&baseline_name))
+            .filter(|result| !result.metrics.cycles_unsupported)
+            .map(|result| {
+                let baseline_cycles = result.metrics.total_num_cycles;
+                WorkloadSavings {
+                    name: result.metrics.name.clone(),
+                    baseline_cycles,
+                    savings: variants
+                        .iter()
+                        .map(|variant| {
+                            results
+                                .get(&variant.results, &result.metrics.name)
+                                .and_then(|run| run.metrics.measured_cycles())
+                                .map(|cycles| i128::from(cycles) - i128::from(baseline_cycles))
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+        comparisons.push(PatchComparison {
+            zkvm: zkvm.to_string(),
+            baseline: baseline_name,
+            patches: patches.clone(),
+            variants,
+            workloads,
+        });
+    }
+    comparisons
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{WorkloadMetrics, WorkloadResult, report::savings_table, results_name};
+
+    /// The manifest and results of a run of succinct with `patches`, where `fft` and `aes` took
+    /// `cycles`.
+    fn run(
+        variant: Option<&str>,
+        patches: &[&str],
+        cycles: [u64; 2],
+    ) -> (RunManifest, Vec<WorkloadResult>) {
+        let manifest = RunManifest {
+            zkvm: "succinct".into(),
+            variant: variant.map(Into::into),
+            patches: Some(patches.iter().map(|&key| key.into()).collect()),
+            started_at: 0,
+            duration_secs: 0,
+            git_commit: None,
+            os: "linux".into(),
+            arch: "x86_64".into(),
+            cpus: 1,
+            corpora: vec!["corpus".into()],
+            blocks: 2,
+        };
+        let results = ["fft", "aes"]
+            .into_iter()
+            .zip(cycles)
+            .map(|(name, cycles)| WorkloadResult {
+                zkvm: results_name("succinct", variant),
+                corpus: "corpus".into(),
+                metrics: WorkloadMetrics::for_test(name, cycles, &[]),
+            })
+            .collect();
+        (manifest, results)
+    }
+
+    #[test]
+    fn savings_are_attributed_to_removed_patches() {
+        let runs = [
+            run(None, &["k256", "sha2"], [1_000, 2_000]),
+            run(Some("without-sha2"), &["k256"], [1_500, 2_000]),
+            run(Some("no-patches"), &[], [3_000, 2_500]),
+        ];
+        let manifests: Vec<RunManifest> =
+            runs.iter().map(|(manifest, _)| manifest.clone()).collect();
+        let results = ResultSet {
+            results: runs.into_iter().flat_map(|(_, results)| results).collect(),
+        };
+
+        let [comparison] = patch_comparisons(&results, &manifests).try_into().unwrap();
+        assert_eq!(comparison.baseline, "succinct");
+        let labels: Vec<String> = comparison.variants.iter().map(|v| v.label()).collect();
+        assert_eq!(labels, ["all patches", "sha2"]);
+        assert_eq!(comparison.workloads[0].name, "fft");
+        assert_eq!(comparison.workloads[0].savings, [Some(2_000), Some(500)]);
+        assert_eq!(comparison.workloads[1].relative(0), Some(0.2));
+
+        let table = savings_table(&comparison).to_string();
+        assert!(table.contains("| Saved by sha2 |"));
+        assert!(table.contains("| Total    |   3000 |"));
+        assert!(table.contains("2500 (45.45%)"));
+    }
+
+    #[test]
+    fn zkvms_without_variants_are_left_out() {
+        let (manifest, _) = run(None, &["k256", "sha2"], [1_000, 2_000]);
+        let mut unrecorded = manifest.clone();
+        unrecorded.variant = Some("no-patches".into());
+        unrecorded.patches = None;
+        assert!(patch_comparisons(&ResultSet::default(), &[manifest, unrecorded]).is_empty());
+    }
+}
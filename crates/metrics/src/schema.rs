@@ -0,0 +1,45 @@
+//! Versioning of the `WorkloadMetrics` JSON schema.
+//!
+//! Every serialized `WorkloadMetrics` carries the `schema_version` it was written with. Files
+//! written before the field existed are version 1. When reading, older entries are migrated step
+//! by step to [`SCHEMA_VERSION`] before being deserialized, so historical results keep loading.
+//!
+//! Adding an optional section (a field with `#[serde(default)]`) does not need a new version.
+//! A new version is only needed when existing fields change meaning or shape, together with a
+//! migration step from the previous version.
+
+use serde_json::{Map, Value};
+
+use crate::MetricsError;
+
+/// Version of the `WorkloadMetrics` schema written by this crate.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Upgrades the JSON entries of a `WorkloadMetrics` list in place to [`SCHEMA_VERSION`].
+///
+/// Entries that are not objects are left for deserialization to report.
+pub(crate) fn migrate(items: &mut [Value]) -> Result<(), MetricsError> {
+    for item in items.iter_mut().filter_map(Value::as_object_mut) {
+        let version = match item.get("schema_version") {
+            None => 1,
+            Some(version) => version
+                .as_u64()
+                .and_then(|version| u32::try_from(version).ok())
+                .ok_or_else(|| MetricsError::InvalidSchemaVersion(version.to_string()))?,
+        };
+        if version > SCHEMA_VERSION {
+            return Err(MetricsError::UnsupportedSchemaVersion(version));
+        }
+        if version < 2 {
+            migrate_v1_to_v2(item);
+        }
+    }
+    Ok(())
+}
+
+/// Version 1 only recorded `name`, `total_num_cycles` and `region_cycles`, for blocks that were
+/// all validated successfully. Version 2 adds the explicit `status`.
+fn migrate_v1_to_v2(item: &mut Map<String, Value>) {
+    item.insert("status".into(), "success".into());
+    item.insert("schema_version".into(), 2.into());
+}
@@ -0,0 +1,258 @@
+//! Aggregations over a [`ResultSet`], used by the metrics CLI.
+
+use std::{cmp::Reverse, collections::BTreeMap};
+
+use crate::{RegionTree, ResultSet, WorkloadResult, WorkloadStatus};
+
+/// Totals of all the workloads that ran on one zkVM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZkvmSummary {
+    /// Name of the zkVM.
+    pub zkvm: String,
+    /// Number of workloads.
+    pub workloads: usize,
+    /// Number of workloads whose status is not `success`.
+    pub failures: usize,
+    /// Number of workloads whose cycles the zkVM did not report.
+    pub unmeasured: usize,
+    /// Sum of the total cycles of the workloads whose cycles were measured.
+    pub total_cycles: u64,
+    /// Sum of the gas used by the workloads that recorded it.
+    pub gas_used: u64,
+    /// Sum of the total cycles of the workloads that recorded their gas used.
+    pub cycles_with_gas: u64,
+    /// Name and total cycles of the most expensive workload.
+    pub most_expensive: Option<(String, u64)>,
+}
+
+impl ZkvmSummary {
+    /// Average total cycles per workload whose cycles were measured, or `None` if there is
+    /// none.
+    #[must_use]
+    pub const fn mean_cycles(&self) -> Option<u64> {
+        self.total_cycles
+            .checked_div((self.workloads - self.unmeasured) as u64)
+    }
+
+    /// Cycles per unit of gas, over the workloads that recorded their gas used.
+    #[must_use]
+    pub fn cycles_per_gas(&self) -> Option<f64> {
+        crate::per_gas(self.cycles_with_gas, self.gas_used)
+    }
+}
+
+/// Cycles of a region, summed over workloads, and their share of the total cycles.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionShare {
+    /// Path of the region.
+    pub path: String,
+    /// Nesting depth of the region, `0` for top-level regions.
+    pub depth: usize,
+    /// Inclusive cycles of the region.
+    pub cycles: u64,
+    /// Cycles of the region itself, outside of its nested regions.
+    pub self_cycles: u64,
+    /// Inclusive cycles as a fraction of the total cycles.
+    pub share: f64,
+}
+
+/// Total cycles of one workload on every zkVM it ran on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkloadComparison {
+    /// Name of the workload.
+    pub name: String,
+    /// Total cycles, keyed by zkVM.
+    pub cycles: BTreeMap<String, u64>,
+}
+
+impl WorkloadComparison {
+    /// Cycles on `zkvm` relative to the cheapest zkVM for this workload (1.0 for the cheapest).
+    #[must_use]
+    pub fn relative_to_cheapest(&self, zkvm: &str) -> Option<f64> {
+        let cycles = *self.cycles.get(zkvm)?;
+        let cheapest = self.cycles.values().copied().min()?;
+        crate::ratio(cycles, cheapest)
+    }
+}
+
+/// Summarizes the workloads of each zkVM.
+#[must_use]
+pub fn zkvm_summaries(results: &ResultSet) -> Vec<ZkvmSummary> {
+    results
+        .zkvms()
+        .into_iter()
+        .map(|zkvm| {
+            let mut summary = ZkvmSummary {
+                zkvm: zkvm.to_string(),
+                workloads: 0,
+                failures: 0,
+                unmeasured: 0,
+                total_cycles: 0,
+                gas_used: 0,
+                cycles_with_gas: 0,
+                most_expensive: None,
+            };
+            for result in results.filter_zkvm(Some(zkvm)) {
+                let metrics = &result.metrics;
+                summary.workloads += 1;
+                if metrics.status != WorkloadStatus::Success {
+                    summary.failures += 1;
+                }
+                let Some(cycles) = metrics.measured_cycles() else {
+                    summary.unmeasured += 1;
+                    continue;
+                };
+                summary.total_cycles += cycles;
+                if let Some(block) = metrics.block {
+                    summary.gas_used += block.gas_used;
+                    summary.cycles_with_gas += cycles;
+                }
+                if summary
+                    .most_expensive
+                    .as_ref()
+                    .is_none_or(|(_, most)| cycles > *most)
+                {
+                    summary.most_expensive = Some((metrics.name.clone(), cycles));
+                }
+            }
+            summary
+        })
+        .collect()
+}
+
+/// Returns the `n` workloads with the most total cycles, on `zkvm` or on any zkVM.
+///
+/// Workloads whose cycles the zkVM did not report are left out.
+#[must_use]
+pub fn top_workloads<'a>(
+    results: &'a ResultSet,
+    zkvm: Option<&'a str>,
+    n: usize,
+) -> Vec<&'a WorkloadResult> {
+    let mut workloads: Vec<_> = results
+        .filter_zkvm(zkvm)
+        .filter(|result| !result.metrics.cycles_unsupported)
+        .collect();
+    workloads.sort_by_key(|result| Reverse(result.metrics.total_num_cycles));
+    workloads.truncate(n);
+    workloads
+}
+
+/// Sums the region cycles of the workloads on `zkvm` (or all zkVMs) and computes the share of
+/// the total cycles spent in each region.
+///
+/// Regions are listed parents first, each followed by its nested regions. Workloads whose cycles
+/// the zkVM did not report are left out.
+#[must_use]
+pub fn region_shares(results: &ResultSet, zkvm: Option<&str>) -> Vec<RegionShare> {
+    let mut tree = RegionTree::default();
+    let mut total_cycles = 0;
+    for result in results
+        .filter_zkvm(zkvm)
+        .filter(|result| !result.metrics.cycles_unsupported)
+    {
+        total_cycles += result.metrics.total_num_cycles;
+        for (path, cycles) in result.metrics.region_cycles.flatten() {
+            tree.insert(&path, cycles);
+        }
+    }
+
+    tree.iter()
+        .map(|node| RegionShare {
+            path: node.path().to_string(),
+            depth: node.path().matches(crate::REGION_PATH_SEPARATOR).count(),
+            cycles: node.inclusive_cycles(),
+            self_cycles: node.self_cycles(),
+            share: crate::ratio(node.inclusive_cycles(), total_cycles).unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Lines up the total cycles of each workload across the zkVMs it ran on.
+///
+/// Workloads are matched by name and ordered by name. A zkVM that did not report the cycles of
+/// a workload is left out of its comparison.
+#[must_use]
+pub fn compare_zkvms(results: &ResultSet) -> Vec<WorkloadComparison> {
+    let mut workloads: BTreeMap<&str, BTreeMap<String, u64>> = BTreeMap::new();
+    for result in &results.results {
+        let Some(cycles) = result.metrics.measured_cycles() else {
+            continue;
+        };
+        workloads
+            .entry(&result.metrics.name)
+            .or_default()
+            .insert(result.zkvm.clone(), cycles);
+    }
+    workloads
+        .into_iter()
+        .map(|(name, cycles)| WorkloadComparison {
+            name: name.to_string(),
+            cycles,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlockMetrics, WorkloadMetrics};
+
+    #[test]
+    fn workloads_are_summarized_per_zkvm() {
+        let mut fft = WorkloadMetrics::for_test("fft", 1_000, &[("compute", 800)]);
+        fft.block = Some(BlockMetrics {
+            gas_used: 71_000,
+            tx_count: 2,
+            witness_size: 4_096,
+        });
+        let aes = WorkloadMetrics::for_test("aes", 2_000, &[]);
+        let cheap_aes = WorkloadMetrics::for_test("aes", 500, &[]);
+        let results =
+            ResultSet::for_test([("risc0", aes), ("succinct", fft), ("succinct", cheap_aes)]);
+
+        let summaries = zkvm_summaries(&results);
+        assert_eq!(
+            summaries[0].most_expensive,
+            Some(("aes".to_string(), 2_000))
+        );
+        assert_eq!(summaries[0].cycles_per_gas(), None);
+        assert_eq!(summaries[1].workloads, 2);
+        assert_eq!(summaries[1].total_cycles, 1_500);
+        assert_eq!(summaries[1].mean_cycles(), Some(750));
+        assert_eq!(summaries[1].cycles_per_gas(), Some(1_000.0 / 71_000.0));
+
+        let top = top_workloads(&results, None, 1);
+        assert_eq!(top[0].metrics.name, "aes");
+        assert_eq!(top[0].zkvm, "risc0");
+
+        let shares = region_shares(&results, Some("succinct"));
+        let compute = shares.iter().find(|r| r.path == "compute").unwrap();
+        assert_eq!(compute.share, 800.0 / 1_500.0);
+
+        let comparisons = compare_zkvms(&results);
+        assert_eq!(comparisons[0].name, "aes");
+        assert_eq!(comparisons[0].relative_to_cheapest("risc0"), Some(4.0));
+    }
+
+    #[test]
+    fn unmeasured_workloads_are_left_out() {
+        let mut unmeasured = WorkloadMetrics::for_test("fft", 0, &[]);
+        unmeasured.cycles_unsupported = true;
+        let measured = WorkloadMetrics::for_test("fft", 2_000, &[("compute", 1_000)]);
+        let results = ResultSet::for_test([("openvm", unmeasured), ("succinct", measured)]);
+
+        let summaries = zkvm_summaries(&results);
+        assert_eq!(summaries[0].unmeasured, 1);
+        assert_eq!(summaries[0].mean_cycles(), None);
+        assert_eq!(summaries[0].most_expensive, None);
+        assert_eq!(summaries[1].mean_cycles(), Some(2_000));
+        assert_eq!(top_workloads(&results, None, 10).len(), 1);
+        assert_eq!(region_shares(&results, None)[0].share, 0.5);
+        assert!(
+            compare_zkvms(&results)
+                .iter()
+                .all(|comparison| !comparison.cycles.contains_key("openvm"))
+        );
+    }
+}
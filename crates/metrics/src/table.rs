@@ -0,0 +1,183 @@
+//! Plain-text tables for the metrics CLI.
+//!
+//! Tables render as Markdown tables with padded columns, so they read well in a terminal and
+//! can be pasted into a Markdown document as they are. They can also be rendered as HTML for
+//! reports.
+
+use std::fmt;
+
+/// Alignment of a table column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    /// Align cells to the left, for text.
+    Left,
+    /// Align cells to the right, for numbers.
+    Right,
+}
+
+/// A table with a header row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    header: Vec<String>,
+    align: Vec<Align>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Creates an empty table with the given column headers, all left-aligned.
+    pub fn new<I, S>(header: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let header: Vec<String> = header.into_iter().map(Into::into).collect();
+        Self {
+            align: vec![Align::Left; header.len()],
+            header,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Right-aligns the columns at the given indices.
+    ///
+    /// Indices past the last column are a bug of the caller: they panic in debug builds and are
+    /// ignored otherwise.
+    #[must_use]
+    pub fn align_right(mut self, columns: &[usize]) -> Self {
+        for &column in columns {
+            debug_assert!(
+                column < self.align.len(),
+                "cannot align column {column} of a table with {} columns",
+                self.align.len()
+            );
+            if let Some(align) = self.align.get_mut(column) {
+                *align = Align::Right;
+            }
+        }
+        self
+    }
+
+    /// Appends a row. Missing cells are left empty and extra cells are dropped.
+    pub fn push_row<I, S>(&mut self, row: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut row: Vec<String> = row.into_iter().map(Into::into).collect();
+        row.resize(self.header.len(), String::new());
+        self.rows.push(row);
+    }
+
+    /// Returns `true` if the table has no rows.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Renders the table as an HTML `<table>`, with right-aligned columns marked by the `num`
+    /// class.
+    #[must_use]
+    pub fn to_html(&self) -> String {
+        let row = |tag: &str, cells: &[String]| {
+            let cells: String = cells
+                .iter()
+                .zip(&self.align)
+                .map(|(cell, align)| match align {
+                    Align::Left => format!("<{tag}>{}</{tag}>", escape_html(cell)),
+                    Align::Right => format!("<{tag} class=\"num\">{}</{tag}>", escape_html(cell)),
+                })
+                .collect();
+            format!("<tr>{cells}</tr>\n")
+        };
+
+        let mut html = String::from("<table>\n<thead>\n");
+        html.push_str(&row("th", &self.header));
+        html.push_str("</thead>\n<tbody>\n");
+        for cells in &self.rows {
+            html.push_str(&row("td", cells));
+        }
+        html.push_str("</tbody>\n</table>\n");
+        html
+    }
+}
+
+/// Escapes the characters of `text` that are special in HTML.
+#[must_use]
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let widths: Vec<usize> = (0..self.header.len())
+            .map(|column| {
+                std::iter::once(&self.header[column])
+                    .chain(self.rows.iter().map(|row| &row[column]))
+                    .map(|cell| cell.chars().count())
+                    .max()
+                    .unwrap_or_default()
+                    // Markdown needs at least three dashes in the separator row.
+                    .max(3)
+            })
+            .collect();
+
+        let write_row = |f: &mut fmt::Formatter<'_>, cells: &[String]| {
+            for ((cell, &width), align) in cells.iter().zip(&widths).zip(&self.align) {
+                match align {
+                    Align::Left => write!(f, "| {cell:<width$} ")?,
+                    Align::Right => write!(f, "| {cell:>width$} ")?,
+                }
+            }
+            writeln!(f, "|")
+        };
+
+        write_row(f, &self.header)?;
+        for (&width, align) in widths.iter().zip(&self.align) {
+            let dashes = "-".repeat(width);
+            match align {
+                Align::Left => write!(f, "| {dashes} ")?,
+                Align::Right => write!(f, "| {}: ", &dashes[1..])?,
+            }
+        }
+        writeln!(f, "|")?;
+        for row in &self.rows {
+            write_row(f, row)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tables_render_as_markdown() {
+        let mut table = Table::new(["zkVM", "Cycles"]).align_right(&[1]);
+        table.push_row(["succinct", "1000"]);
+        assert_eq!(
+            table.to_string(),
+            "| zkVM     | Cycles |\n\
+             | -------- | -----: |\n\
+             | succinct |   1000 |\n"
+        );
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, should_panic(expected = "cannot align column 2"))]
+    fn aligning_a_missing_column_is_a_bug() {
+        let table = Table::new(["zkVM", "Cycles"]).align_right(&[2]);
+        assert_eq!(table.align, [Align::Left, Align::Left]);
+    }
+}
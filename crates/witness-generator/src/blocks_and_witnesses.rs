@@ -21,6 +21,11 @@ pub struct BlocksAndWitnesses {
     // TODO: Also Genesis file is wrong in chainspec
     // TODO: We can keep this initially and don't measure the time it takes to deserialize
     pub network: Genesis,
+    /// Exception the last block in `blocks_and_witnesses` is expected to be rejected with.
+    ///
+    /// `None` if every block in the test case is expected to be valid.
+    #[serde(default)]
+    pub expected_exception: Option<String>,
 }
 
 /// Errors that can occur during serialization or deserialization of `BlocksAndWitnesses`.
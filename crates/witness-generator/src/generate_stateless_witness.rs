@@ -1,31 +1,43 @@
 use ef_tests::{
-    Case,
+    Case, Error,
     cases::blockchain_test::{BlockchainTestCase, run_case},
+    models::BlockchainTest,
 };
 use reth_chainspec::ChainSpec;
 use std::path::{Path, PathBuf};
 use walkdir::{DirEntry, WalkDir};
 
-use crate::BlocksAndWitnesses;
+use crate::{BlocksAndWitnesses, state_test::StateTest};
 use reth_stateless::ClientInput;
 
 /// Root directory for the relevant blockchain tests within the `zkevm-fixtures` submodule.
 const BLOCKCHAIN_TEST_DIR: &str = "blockchain_tests";
 
-/// Generates `BlocksAndWitnesses` for all valid blockchain test cases found
-/// within the specified `BLOCKCHAIN_TEST_DIR` directory in `zkevm-fixtures`.
+/// Root directory for the state tests within the `zkevm-fixtures` submodule.
+const STATE_TEST_DIR: &str = "state_tests";
+
+/// Generates `BlocksAndWitnesses` for all blockchain test cases found
+/// within the specified `BLOCKCHAIN_TEST_DIR` directory in `zkevm-fixtures`,
+/// followed by those of the state tests found within `STATE_TEST_DIR`, if any
+/// (see [`generate_state_tests`]).
+///
+/// Test cases containing a block that is expected to be rejected keep that block
+/// (see [`BlocksAndWitnesses::expected_exception`]).
 ///
 /// It walks the target directory, parses each JSON test file, executes the test
 /// using `ef_tests`, collects the resulting block/witness pairs, and packages them.
 ///
 /// Uses `rayon` for parallel processing of test cases within a single file.
 ///
+/// Test cases that `ef_tests` fails to run, other than on the block the test expects to
+/// be rejected, and test cases whose expected rejection does not happen, are skipped with
+/// a warning rather than aborting the whole corpus.
+///
 /// # Panics
 ///
 /// - If the `zkevm-fixtures` directory cannot be located relative to the crate root.
 /// - If the target `BLOCKCHAIN_TEST_DIR` directory does not exist.
 /// - If a JSON test case file cannot be parsed.
-/// - If `ef_tests::cases::blockchain_test::run_case` fails for a test.
 pub fn generate() -> Vec<BlocksAndWitnesses> {
     // First get the path to "BLOCKCHAIN_TEST_DIR"
     // TODO: Maybe we should have this be passed as a parameter in the future
@@ -54,22 +66,133 @@ pub fn generate() -> Vec<BlocksAndWitnesses> {
             // This is why we have `tests`.
             .tests
             .iter()
-            .map(|(name, case)| BlocksAndWitnesses {
-                name: name.to_string(),
-                blocks_and_witnesses: run_case(case)
-                    .unwrap()
-                    .into_iter()
-                    .map(|(block, witness)| ClientInput { block, witness })
-                    .collect(),
-                network: ChainSpec::from(case.network).genesis,
+            .filter_map(|(name, case)| {
+                let (blocks_and_witnesses, expected_exception) =
+                    match run_case_keeping_invalid_block(case) {
+                        Ok(outcome) => outcome,
+                        Err(err) => {
+                            eprintln!("skipping blockchain test {name}: {err}");
+                            return None;
+                        }
+                    };
+                Some(BlocksAndWitnesses {
+                    name: name.to_string(),
+                    blocks_and_witnesses,
+                    network: ChainSpec::from(case.network).genesis,
+                    expected_exception,
+                })
             })
             .collect();
         blocks_and_witnesses.extend(blockchain_case);
     }
 
+    if path_to_zkevm_fixtures(STATE_TEST_DIR).exists() {
+        blocks_and_witnesses.extend(generate_state_tests());
+    }
+
+    blocks_and_witnesses
+}
+
+/// Generates `BlocksAndWitnesses` for all state tests found within the
+/// `STATE_TEST_DIR` directory in `zkevm-fixtures`.
+///
+/// A state test applies a single transaction to a pre-state, for one or more
+/// forks. Each of its outcomes becomes a test case of a single block holding
+/// the transaction, on top of a genesis block holding the pre-state. Outcomes
+/// on forks before Paris or unknown to `ef-tests`, outcomes whose block cannot
+/// be built and outcomes that fail to run are skipped with a warning.
+///
+/// # Panics
+///
+/// - If the `zkevm-fixtures` directory cannot be located relative to the crate root.
+/// - If the target `STATE_TEST_DIR` directory does not exist.
+/// - If a JSON state test file cannot be parsed.
+pub fn generate_state_tests() -> Vec<BlocksAndWitnesses> {
+    let suite_path = path_to_zkevm_fixtures(STATE_TEST_DIR);
+    assert!(
+        suite_path.exists(),
+        "Test suite path does not exist: {suite_path:?}"
+    );
+
+    let mut blocks_and_witnesses = Vec::new();
+    for test_case_path in find_all_files_with_extension(&suite_path, ".json") {
+        // As with blockchain tests, a JSON file can hold several state tests.
+        for (name, state_test) in StateTest::load(&test_case_path) {
+            let tests = state_test.blockchain_tests(&name);
+            for (name, reason) in tests.skipped {
+                eprintln!("skipping state test {name}: {reason}");
+            }
+            for (name, case) in tests.tests {
+                let (client_inputs, expected_exception) =
+                    match run_case_keeping_invalid_block(&case) {
+                        Ok(outcome) => outcome,
+                        Err(err) => {
+                            eprintln!("skipping state test {name}: {err}");
+                            continue;
+                        }
+                    };
+                blocks_and_witnesses.push(BlocksAndWitnesses {
+                    name,
+                    blocks_and_witnesses: client_inputs,
+                    network: ChainSpec::from(case.network).genesis,
+                    expected_exception,
+                });
+            }
+        }
+    }
+
     blocks_and_witnesses
 }
 
+/// Runs `case` and returns its block/witness pairs, together with the exception the last
+/// block is expected to be rejected with.
+///
+/// `run_case` stops at the first block that fails. When that block is the one the test expects
+/// to be rejected, it is kept as the last pair, with the witness `run_case` generated for it
+/// before failing. Blocks following it are not included.
+///
+/// # Errors
+///
+/// - If `run_case` fails on any other block.
+/// - If the test expects a block to be rejected but `run_case` accepts every block.
+pub(crate) fn run_case_keeping_invalid_block(
+    case: &BlockchainTest,
+) -> Result<(Vec<ClientInput>, Option<String>), String> {
+    let invalid_block = case
+        .blocks
+        .iter()
+        .enumerate()
+        .find_map(|(index, block)| Some((index, block.expect_exception.clone()?)));
+
+    // `BlockProcessingFailed` carries the inputs generated up to and including the failing
+    // block; it is specific to the reth fork pinned in the workspace manifest.
+    let program_inputs = match (run_case(case), &invalid_block) {
+        (Ok(program_inputs), None) => program_inputs,
+        (Ok(_), Some((index, exception))) => {
+            return Err(format!(
+                "block {index} was expected to fail with {exception}, but was accepted"
+            ));
+        }
+        (
+            Err(Error::BlockProcessingFailed {
+                partial_program_inputs,
+                ..
+            }),
+            Some((index, _)),
+        ) if partial_program_inputs.len() == index + 1 => partial_program_inputs,
+        (Err(err), _) => return Err(format!("failed to run blockchain test: {err}")),
+    };
+
+    let blocks_and_witnesses = program_inputs
+        .into_iter()
+        .map(|(block, witness)| ClientInput { block, witness })
+        .collect();
+    Ok((
+        blocks_and_witnesses,
+        invalid_block.map(|(_, exception)| exception),
+    ))
+}
+
 /// Recursively finds all files within `path` that end with `extension`.
 // This function was copied from `ef-tests`
 fn find_all_files_with_extension(path: &Path, extension: &str) -> Vec<PathBuf> {
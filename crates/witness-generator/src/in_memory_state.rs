@@ -0,0 +1,244 @@
+//! State of a chain held in memory, to execute its blocks without a node.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use alloy_consensus::Header;
+use alloy_eips::eip7685::Requests;
+use alloy_genesis::Genesis;
+use alloy_primitives::{Address, B256, Bytes, KECCAK_EMPTY, U256, keccak256, logs_bloom};
+use reth_chainspec::ChainSpec;
+use reth_ethereum_primitives::{Block, Receipt};
+use reth_evm::{
+    ConfigureEvm,
+    execute::{BlockExecutionError, BlockExecutor},
+};
+use reth_evm_ethereum::EthEvmConfig;
+use reth_execution_errors::TrieWitnessError;
+use reth_primitives_traits::{Account, RecoveredBlock};
+use reth_revm::{
+    db::{BundleState, CacheDB, EmptyDB, State, states::bundle_state::BundleRetention},
+    witness::ExecutionWitnessRecord,
+};
+use reth_trie::{
+    HashedPostState, HashedStorage,
+    hashed_cursor::{HashedPostStateCursorFactory, noop::NoopHashedCursorFactory},
+    trie_cursor::noop::NoopTrieCursorFactory,
+    witness::TrieWitness,
+};
+use reth_trie_common::{
+    TrieAccount,
+    root::{state_root_unhashed, storage_root_unhashed},
+};
+use revm::{bytecode::Bytecode, state::AccountInfo};
+
+/// Every account of a chain's state, with its storage.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct InMemoryState {
+    accounts: BTreeMap<Address, (AccountInfo, BTreeMap<U256, U256>)>,
+}
+
+/// Outcome of executing a block on top of an [`InMemoryState`].
+#[derive(Debug)]
+pub(crate) struct Execution {
+    /// Receipts of the transactions of the block.
+    pub(crate) receipts: Vec<Receipt>,
+    /// Requests of the block, from Prague on.
+    pub(crate) requests: Requests,
+    /// Changes the block made to the state.
+    pub(crate) bundle: BundleState,
+    /// Accounts, storage slots, code and block hashes the block accessed.
+    pub(crate) record: ExecutionWitnessRecord,
+}
+
+impl InMemoryState {
+    /// Creates the state of the genesis block of `genesis`, i.e., its allocation.
+    pub(crate) fn from_genesis(genesis: &Genesis) -> Self {
+        let mut state = Self::default();
+        for (address, account) in &genesis.alloc {
+            state.insert_account(
+                *address,
+                account.balance,
+                account.nonce.unwrap_or_default(),
+                account.code.clone().unwrap_or_default(),
+                account.storage.iter().flatten().map(|(slot, value)| {
+                    (U256::from_be_bytes(slot.0), U256::from_be_bytes(value.0))
+                }),
+            );
+        }
+        state
+    }
+
+    /// Adds an account, replacing any account at `address`.
+    pub(crate) fn insert_account(
+        &mut self,
+        address: Address,
+        balance: U256,
+        nonce: u64,
+        code: Bytes,
+        storage: impl IntoIterator<Item = (U256, U256)>,
+    ) {
+        let code = Bytecode::new_raw(code);
+        let info = AccountInfo {
+            balance,
+            nonce,
+            code_hash: code.hash_slow(),
+            code: Some(code),
+        };
+        let storage = storage
+            .into_iter()
+            .filter(|(_, value)| !value.is_zero())
+            .collect();
+        self.accounts.insert(address, (info, storage));
+    }
+
+    /// Executes `block`, whose ancestors have the given numbers and hashes, on top of the state.
+    ///
+    /// The state is left untouched: see [`Self::commit`].
+    pub(crate) fn execute(
+        &self,
+        chain_spec: Arc<ChainSpec>,
+        block: &RecoveredBlock<Block>,
+        ancestor_hashes: impl IntoIterator<Item = (u64, B256)>,
+    ) -> Result<Execution, BlockExecutionError> {
+        let mut database = CacheDB::new(EmptyDB::default());
+        for (address, (info, storage)) in &self.accounts {
+            database.insert_account_info(*address, info.clone());
+            for (slot, value) in storage {
+                // The empty database below the cache cannot fail.
+                let Ok(()) = database.insert_account_storage(*address, *slot, *value);
+            }
+        }
+        for (number, hash) in ancestor_hashes {
+            database.cache.block_hashes.insert(U256::from(number), hash);
+        }
+
+        let mut db = State::builder()
+            .with_database(database)
+            .with_bundle_update()
+            .without_state_clear()
+            .build();
+        let evm_config = EthEvmConfig::new(chain_spec);
+        let mut executor = evm_config.executor_for_block(&mut db, block.sealed_block());
+        executor.apply_pre_execution_changes()?;
+        for tx in block.transactions_recovered() {
+            executor.execute_transaction(tx)?;
+        }
+        let output = executor.apply_post_execution_changes()?;
+        let record = ExecutionWitnessRecord::from_executed_state(&db);
+        db.merge_transitions(BundleRetention::Reverts);
+        Ok(Execution {
+            receipts: output.receipts,
+            requests: output.requests,
+            bundle: db.take_bundle(),
+            record,
+        })
+    }
+
+    /// Applies the changes in `bundle` to the state.
+    pub(crate) fn commit(&mut self, bundle: &BundleState) {
+        for (address, account) in &bundle.state {
+            let Some(info) = &account.info else {
+                self.accounts.remove(address);
+                continue;
+            };
+            let (current, storage) = self.accounts.entry(*address).or_default();
+            let mut info = info.clone();
+            if info.code.is_none() {
+                // Only new code is in the bundle, the rest stays with the account.
+                info.code = bundle
+                    .contracts
+                    .get(&info.code_hash)
+                    .or_else(|| {
+                        current
+                            .code
+                            .as_ref()
+                            .filter(|_| current.code_hash == info.code_hash)
+                    })
+                    .cloned();
+            }
+            *current = info;
+            if account.was_destroyed() {
+                storage.clear();
+            }
+            for (slot, value) in &account.storage {
+                if value.present_value.is_zero() {
+                    storage.remove(slot);
+                } else {
+                    storage.insert(*slot, value.present_value);
+                }
+            }
+        }
+    }
+
+    /// Returns the root of the state.
+    pub(crate) fn state_root(&self) -> B256 {
+        state_root_unhashed(self.accounts.iter().map(|(address, (info, storage))| {
+            let storage_root = storage_root_unhashed(
+                storage
+                    .iter()
+                    .map(|(slot, value)| (B256::new(slot.to_be_bytes()), *value)),
+            );
+            let account = TrieAccount {
+                nonce: info.nonce,
+                balance: info.balance,
+                storage_root,
+                code_hash: info.code_hash,
+            };
+            (*address, account)
+        }))
+    }
+
+    /// Returns the trie nodes proving what `record` accessed against the root of the state,
+    /// as `debug_executionWitness` does.
+    pub(crate) fn witness(
+        &self,
+        record: &ExecutionWitnessRecord,
+    ) -> Result<Vec<Bytes>, TrieWitnessError> {
+        let mut state = HashedPostState::default();
+        for (address, (info, storage)) in &self.accounts {
+            let hashed_address = keccak256(address);
+            let account = Account {
+                nonce: info.nonce,
+                balance: info.balance,
+                bytecode_hash: (info.code_hash != KECCAK_EMPTY).then_some(info.code_hash),
+            };
+            state.accounts.insert(hashed_address, Some(account));
+            state.storages.insert(
+                hashed_address,
+                HashedStorage::from_iter(
+                    false,
+                    storage
+                        .iter()
+                        .map(|(slot, value)| (keccak256(B256::new(slot.to_be_bytes())), *value)),
+                ),
+            );
+        }
+        let state = state.into_sorted();
+
+        let nodes = TrieWitness::new(
+            NoopTrieCursorFactory,
+            HashedPostStateCursorFactory::new(NoopHashedCursorFactory, &state),
+        )
+        .compute(record.hashed_state.clone())?;
+        Ok(nodes.into_values().collect())
+    }
+}
+
+impl Execution {
+    /// Fills in what `header` commits to about the outcome of the execution of its block: gas
+    /// used, receipts root, logs bloom and, from Prague on, requests hash.
+    ///
+    /// The state root is left to the caller, once the changes are committed.
+    pub(crate) fn complete_header(&self, header: &mut Header) {
+        header.gas_used = self
+            .receipts
+            .last()
+            .map(|receipt| receipt.cumulative_gas_used)
+            .unwrap_or_default();
+        header.receipts_root = Receipt::calculate_receipt_root_no_memo(&self.receipts);
+        header.logs_bloom = logs_bloom(self.receipts.iter().flat_map(|receipt| &receipt.logs));
+        if header.requests_hash.is_some() {
+            header.requests_hash = Some(self.requests.requests_hash());
+        }
+    }
+}
@@ -3,5 +3,11 @@
 mod blocks_and_witnesses;
 /// generate the execution witnesses for `zkevm-fixtures`
 pub mod generate_stateless_witness;
+mod in_memory_state;
+/// generate the execution witnesses of a chain of RLP-encoded blocks
+pub mod rlp_chain;
+/// import real-chain blocks and execution witnesses from recorded RPC responses
+pub mod rpc_dump;
+mod state_test;
 
 pub use blocks_and_witnesses::{BlocksAndWitnesses, BwError, ClientInput};
@@ -0,0 +1,463 @@
+//! Execution witnesses for a chain given as RLP-encoded blocks on top of a genesis file.
+//!
+//! The blocks are those a client exports (e.g., `geth export` or `reth export`): RLP-encoded
+//! one after the other, in ascending order, starting with the genesis block or its child. They
+//! are executed in memory on top of the genesis allocation, and each one is paired with the
+//! execution witness proving what it accessed.
+//!
+//! A chain directory holds both files:
+//!
+//! ```text
+//! <chain>/
+//! ├── genesis.json
+//! └── chain.rlp
+//! ```
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use alloy_consensus::Header;
+use alloy_genesis::Genesis;
+use alloy_primitives::{B256, Bytes};
+use alloy_rlp::Decodable;
+use reth_chainspec::{ChainSpec, EthChainSpec};
+use reth_ethereum_consensus::validate_block_post_execution;
+use reth_ethereum_primitives::Block;
+use reth_execution_errors::TrieWitnessError;
+use reth_primitives_traits::Block as _;
+use reth_stateless::{ClientInput, ExecutionWitness};
+use thiserror::Error;
+
+use crate::{BlocksAndWitnesses, in_memory_state::InMemoryState};
+
+/// Genesis file of the chain, in a chain directory.
+pub const GENESIS_FILE_NAME: &str = "genesis.json";
+/// RLP-encoded blocks of the chain, in a chain directory.
+pub const CHAIN_FILE_NAME: &str = "chain.rlp";
+
+/// Errors that can occur while executing a chain to generate its execution witnesses.
+#[derive(Error, Debug)]
+pub enum RlpChainError {
+    /// Error reading a file or directory.
+    #[error("failed to read {path}: {source}")]
+    Io {
+        /// Path that could not be read.
+        path: PathBuf,
+        /// Underlying error.
+        #[source]
+        source: io::Error,
+    },
+
+    /// Serde JSON (de)serialization error, e.g., of the genesis file.
+    #[error("serde JSON (de)serialization error: {0}")]
+    Serde(#[from] serde_json::Error),
+
+    /// A block could not be decoded.
+    #[error("failed to decode block {index} of the chain: {source}")]
+    Rlp {
+        /// Position of the block in the chain file.
+        index: usize,
+        /// Underlying error.
+        #[source]
+        source: alloy_rlp::Error,
+    },
+
+    /// A block does not extend the block before it, or the genesis block.
+    #[error("block {number} ({hash}) does not extend block {parent_number} ({parent_hash})")]
+    Disconnected {
+        /// Number of the block.
+        number: u64,
+        /// Hash of the block.
+        hash: B256,
+        /// Number of the block it should extend.
+        parent_number: u64,
+        /// Hash of the block it should extend.
+        parent_hash: B256,
+    },
+
+    /// The signer of a transaction of a block could not be recovered.
+    #[error("failed to recover the transaction signers of block {number}")]
+    SignerRecovery {
+        /// Number of the block.
+        number: u64,
+    },
+
+    /// The execution witness of a block could not be computed.
+    #[error("failed to compute the execution witness of block {number}: {source}")]
+    Witness {
+        /// Number of the block.
+        number: u64,
+        /// Underlying error.
+        #[source]
+        source: TrieWitnessError,
+    },
+
+    /// A block failed to execute, or its header does not match the outcome of its execution.
+    #[error("block {number} is invalid: {reason}")]
+    InvalidBlock {
+        /// Number of the block.
+        number: u64,
+        /// Why the block is invalid.
+        reason: String,
+    },
+}
+
+/// Decodes the blocks of a chain file: RLP-encoded blocks, one after the other.
+///
+/// # Errors
+///
+/// Returns `RlpChainError::Rlp` if a block cannot be decoded.
+pub fn decode_blocks(mut rlp: &[u8]) -> Result<Vec<Block>, RlpChainError> {
+    let mut blocks = Vec::new();
+    while !rlp.is_empty() {
+        let block = Block::decode(&mut rlp).map_err(|source| RlpChainError::Rlp {
+            index: blocks.len(),
+            source,
+        })?;
+        blocks.push(block);
+    }
+    Ok(blocks)
+}
+
+/// Executes `blocks` on top of `genesis`, and pairs each of them with its execution witness, as
+/// a test case named `name`.
+///
+/// `blocks` may start with the genesis block, which is skipped.
+///
+/// # Errors
+///
+/// Returns an error if a block does not extend the one before it, fails to execute, has a
+/// header that does not match the outcome of its execution, or its witness cannot be computed.
+pub fn generate(
+    name: impl Into<String>,
+    genesis: Genesis,
+    blocks: Vec<Block>,
+) -> Result<BlocksAndWitnesses, RlpChainError> {
+    let chain_spec = Arc::new(ChainSpec::from(genesis.clone()));
+    let mut state = InMemoryState::from_genesis(&genesis);
+
+    // Headers of every block so far, with their hashes, genesis first, for `BLOCKHASH` and the
+    // witnesses.
+    let genesis_hash = chain_spec.genesis_hash();
+    let mut ancestors: Vec<(Header, B256)> =
+        vec![(chain_spec.genesis_header().clone(), genesis_hash)];
+    let mut blocks_and_witnesses = Vec::with_capacity(blocks.len());
+    for block in blocks {
+        let hash = block.header.hash_slow();
+        if block.header.number == 0 && hash == genesis_hash {
+            continue;
+        }
+        let (parent, parent_hash) = ancestors.last().expect("the genesis block is an ancestor");
+        let parent_hash = *parent_hash;
+        if block.header.parent_hash != parent_hash || block.header.number != parent.number + 1 {
+            return Err(RlpChainError::Disconnected {
+                number: block.header.number,
+                hash,
+                parent_number: parent.number,
+                parent_hash,
+            });
+        }
+        let number = block.header.number;
+        let invalid = |reason: String| RlpChainError::InvalidBlock { number, reason };
+
+        let recovered = block
+            .clone()
+            .try_into_recovered()
+            .map_err(|_| RlpChainError::SignerRecovery { number })?;
+        let execution = state
+            .execute(
+                chain_spec.clone(),
+                &recovered,
+                ancestors
+                    .iter()
+                    .map(|(header, hash)| (header.number, *hash)),
+            )
+            .map_err(|err| invalid(err.to_string()))?;
+        validate_block_post_execution(
+            &recovered,
+            &chain_spec,
+            &execution.receipts,
+            &execution.requests,
+        )
+        .map_err(|err| invalid(err.to_string()))?;
+
+        // The witness proves the accesses against the parent's state, so it is taken before the
+        // block's changes are applied.
+        let nodes = state
+            .witness(&execution.record)
+            .map_err(|source| RlpChainError::Witness { number, source })?;
+        state.commit(&execution.bundle);
+        let state_root = state.state_root();
+        if state_root != block.header.state_root {
+            return Err(invalid(format!(
+                "state root mismatch: got {state_root}, expected {}",
+                block.header.state_root
+            )));
+        }
+
+        // Like `debug_executionWitness`, include the headers from the oldest block whose hash
+        // was accessed up to the parent.
+        let oldest = execution
+            .record
+            .lowest_block_number
+            .unwrap_or(number - 1)
+            .min(number - 1);
+        let witness = ExecutionWitness {
+            state: nodes,
+            codes: execution.record.codes,
+            keys: execution.record.keys,
+            headers: ancestors
+                .iter()
+                .filter(|(header, _)| header.number >= oldest)
+                .map(|(header, _)| Bytes::from(alloy_rlp::encode(header)))
+                .collect(),
+        };
+
+        ancestors.push((block.header.clone(), hash));
+        blocks_and_witnesses.push(ClientInput { block, witness });
+    }
+
+    Ok(BlocksAndWitnesses {
+        name: name.into(),
+        blocks_and_witnesses,
+        network: genesis,
+        expected_exception: None,
+    })
+}
+
+/// Executes the chain in `dir`, holding a `genesis.json` and a `chain.rlp`, as a test case named
+/// after the directory.
+///
+/// # Errors
+///
+/// Returns an error if either file cannot be read or decoded, or [`generate`] fails.
+pub fn generate_from_dir(dir: &Path) -> Result<BlocksAndWitnesses, RlpChainError> {
+    let read = |file_name: &str| {
+        let path = dir.join(file_name);
+        fs::read(&path).map_err(|source| RlpChainError::Io { path, source })
+    };
+    let genesis = serde_json::from_slice(&read(GENESIS_FILE_NAME)?)?;
+    let blocks = decode_blocks(&read(CHAIN_FILE_NAME)?)?;
+    let name = dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    generate(name, genesis, blocks)
+}
+
+/// Executes each chain found directly within `dir`, i.e., each subdirectory holding a
+/// `chain.rlp`, in the order of their names.
+///
+/// # Errors
+///
+/// Returns the first error [`generate_from_dir`] returns.
+pub fn generate_all(dir: &Path) -> Result<Vec<BlocksAndWitnesses>, RlpChainError> {
+    let io_error = |source| RlpChainError::Io {
+        path: dir.to_path_buf(),
+        source,
+    };
+    let mut chains = Vec::new();
+    for entry in fs::read_dir(dir).map_err(io_error)? {
+        let path = entry.map_err(io_error)?.path();
+        if path.join(CHAIN_FILE_NAME).is_file() {
+            chains.push(path);
+        }
+    }
+    chains.sort();
+    chains
+        .iter()
+        .map(|chain| generate_from_dir(chain))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::slice;
+
+    use alloy_consensus::{
+        SignableTransaction, TxLegacy,
+        constants::{EMPTY_OMMER_ROOT_HASH, EMPTY_ROOT_HASH},
+        proofs::calculate_transaction_root,
+    };
+    use alloy_eips::{
+        eip1559::BaseFeeParams,
+        eip2718::{Decodable2718, Encodable2718},
+        eip4895::Withdrawals,
+    };
+    use alloy_primitives::{Address, TxKind, U256, address};
+    use alloy_signer::SignerSync;
+    use alloy_signer_local::PrivateKeySigner;
+    use reth_ethereum_primitives::{BlockBody, TransactionSigned};
+    use reth_stateless::validation::stateless_validation;
+    use serde_json::json;
+
+    use super::*;
+
+    const RECIPIENT: Address = address!("0x00000000000000000000000000000000000000aa");
+
+    fn signer() -> PrivateKeySigner {
+        PrivateKeySigner::from_bytes(&B256::repeat_byte(0x11)).unwrap()
+    }
+
+    /// A Cancun genesis funding `signer`.
+    fn genesis(signer: Address) -> Genesis {
+        serde_json::from_value(json!({
+            "config": {
+                "chainId": 1,
+                "homesteadBlock": 0,
+                "eip150Block": 0,
+                "eip155Block": 0,
+                "eip158Block": 0,
+                "byzantiumBlock": 0,
+                "constantinopleBlock": 0,
+                "petersburgBlock": 0,
+                "istanbulBlock": 0,
+                "berlinBlock": 0,
+                "londonBlock": 0,
+                "mergeNetsplitBlock": 0,
+                "terminalTotalDifficulty": 0,
+                "terminalTotalDifficultyPassed": true,
+                "shanghaiTime": 0,
+                "cancunTime": 0,
+            },
+            "gasLimit": "0x1c9c380",
+            "difficulty": "0x0",
+            "alloc": {
+                signer.to_string(): {"balance": "0xde0b6b3a7640000"},
+            },
+        }))
+        .unwrap()
+    }
+
+    /// The genesis block of `genesis`, followed by `len` blocks each transferring one wei from
+    /// `signer`.
+    fn chain(signer: &PrivateKeySigner, genesis: &Genesis, len: u64) -> Vec<Block> {
+        let chain_spec = Arc::new(ChainSpec::from(genesis.clone()));
+        let mut state = InMemoryState::from_genesis(genesis);
+        let mut blocks = vec![Block {
+            header: chain_spec.genesis_header().clone(),
+            body: BlockBody {
+                withdrawals: Some(Withdrawals::default()),
+                ..Default::default()
+            },
+        }];
+        for nonce in 0..len {
+            let parent = &blocks.last().unwrap().header;
+            let tx = TxLegacy {
+                chain_id: Some(1),
+                nonce,
+                gas_price: 2_000_000_000,
+                gas_limit: 21_000,
+                to: TxKind::Call(RECIPIENT),
+                value: U256::from(1),
+                input: Bytes::new(),
+            };
+            let signature = signer.sign_hash_sync(&tx.signature_hash()).unwrap();
+            let encoded = tx.into_signed(signature).encoded_2718();
+            let tx = TransactionSigned::decode_2718(&mut encoded.as_slice()).unwrap();
+            let header = Header {
+                parent_hash: parent.hash_slow(),
+                ommers_hash: EMPTY_OMMER_ROOT_HASH,
+                transactions_root: calculate_transaction_root(slice::from_ref(&tx)),
+                withdrawals_root: Some(EMPTY_ROOT_HASH),
+                number: parent.number + 1,
+                gas_limit: parent.gas_limit,
+                timestamp: parent.timestamp + 12,
+                base_fee_per_gas: parent.next_block_base_fee(BaseFeeParams::ethereum()),
+                blob_gas_used: Some(0),
+                excess_blob_gas: Some(0),
+                parent_beacon_block_root: Some(B256::ZERO),
+                ..Default::default()
+            };
+            let mut block = Block {
+                header,
+                body: BlockBody {
+                    transactions: vec![tx],
+                    ommers: Vec::new(),
+                    withdrawals: Some(Withdrawals::default()),
+                },
+            };
+
+            let recovered = block.clone().try_into_recovered().unwrap();
+            let ancestors = blocks
+                .iter()
+                .map(|block| (block.header.number, block.header.hash_slow()));
+            let execution = state
+                .execute(chain_spec.clone(), &recovered, ancestors)
+                .unwrap();
+            state.commit(&execution.bundle);
+            execution.complete_header(&mut block.header);
+            block.header.state_root = state.state_root();
+            blocks.push(block);
+        }
+        blocks
+    }
+
+    #[test]
+    fn blocks_are_decoded_one_after_the_other() {
+        let signer = signer();
+        let blocks = chain(&signer, &genesis(signer.address()), 2);
+        let rlp: Vec<u8> = blocks.iter().flat_map(alloy_rlp::encode).collect();
+        let decoded = decode_blocks(&rlp).unwrap();
+        assert_eq!(decoded, blocks);
+        assert!(decode_blocks(&[]).unwrap().is_empty());
+
+        // The last block is cut short.
+        let err = decode_blocks(&rlp[..rlp.len() - 1]).unwrap_err();
+        assert!(matches!(err, RlpChainError::Rlp { index: 2, .. }), "{err}");
+    }
+
+    #[test]
+    fn each_block_is_paired_with_a_witness_that_validates_it() {
+        let signer = signer();
+        let genesis = genesis(signer.address());
+        let blocks = chain(&signer, &genesis, 2);
+        let rlp: Vec<u8> = blocks.iter().flat_map(alloy_rlp::encode).collect();
+
+        let chain = generate("transfers", genesis.clone(), decode_blocks(&rlp).unwrap()).unwrap();
+        assert_eq!(chain.name, "transfers");
+        assert_eq!(chain.expected_exception, None);
+        // The genesis block is skipped.
+        assert_eq!(chain.blocks_and_witnesses.len(), 2);
+
+        let chain_spec = Arc::new(ChainSpec::from(genesis));
+        for (input, block) in chain.blocks_and_witnesses.into_iter().zip(&blocks[1..]) {
+            assert_eq!(input.block, *block);
+            let number = block.header.number;
+            if let Err(err) = stateless_validation(input.block, input.witness, chain_spec.clone()) {
+                panic!("block {number} failed stateless validation: {err}");
+            }
+        }
+    }
+
+    #[test]
+    fn disconnected_and_invalid_blocks_are_errors() {
+        let signer = signer();
+        let genesis = genesis(signer.address());
+        let blocks = chain(&signer, &genesis, 2);
+
+        let err = generate("gap", genesis.clone(), vec![blocks[2].clone()]).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                RlpChainError::Disconnected {
+                    number: 2,
+                    parent_number: 0,
+                    ..
+                }
+            ),
+            "{err}"
+        );
+
+        let mut block = blocks[1].clone();
+        block.header.state_root = B256::repeat_byte(0xff);
+        let err = generate("bad_state_root", genesis, vec![block]).unwrap_err();
+        assert!(
+            matches!(err, RlpChainError::InvalidBlock { number: 1, .. }),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,489 @@
+//! Real-chain blocks and their execution witnesses, from recorded RPC responses.
+//!
+//! A node serves everything a guest needs to validate one of its blocks: the block itself
+//! (`debug_getRawBlock`) and its execution witness (`debug_executionWitness`). A dump keeps the
+//! responses of a node for some blocks, next to the genesis file of its chain:
+//!
+//! ```text
+//! <dump>/
+//! ├── genesis.json                        # chain spec, with its `config`
+//! └── <block number>/
+//!     ├── debug_getRawBlock.json
+//!     └── debug_executionWitness.json
+//! ```
+//!
+//! Each file holds the response to the method it is named after, called with the block number:
+//! either the whole JSON-RPC response, or only its `result`.
+//!
+//! [`RecordedRpc`] serves the responses of a dump, and [`FakeRpc`] responses held in memory, as a
+//! stand-in for a node in tests.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use alloy_genesis::Genesis;
+use alloy_primitives::Bytes;
+use alloy_rlp::Decodable;
+use reth_ethereum_primitives::Block;
+use reth_stateless::{ClientInput, ExecutionWitness};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::BlocksAndWitnesses;
+
+/// Method returning the RLP encoding of a block.
+pub const RAW_BLOCK_METHOD: &str = "debug_getRawBlock";
+/// Method returning the execution witness of a block.
+pub const EXECUTION_WITNESS_METHOD: &str = "debug_executionWitness";
+/// Genesis file of the chain, in a dump.
+pub const GENESIS_FILE_NAME: &str = "genesis.json";
+
+/// Errors that can occur while importing blocks and witnesses from RPC responses.
+#[derive(Error, Debug)]
+pub enum RpcDumpError {
+    /// Error reading a file or directory of a dump.
+    #[error("failed to read {path}: {source}")]
+    Io {
+        /// Path that could not be read.
+        path: PathBuf,
+        /// Underlying error.
+        #[source]
+        source: io::Error,
+    },
+
+    /// No response to a method is available for a block.
+    #[error("no response to `{method}` for block {number}")]
+    Missing {
+        /// Method called.
+        method: String,
+        /// Block the method was called for.
+        number: u64,
+    },
+
+    /// The node answered a method with an error.
+    #[error("`{method}` failed for block {number}: {message}")]
+    Rpc {
+        /// Method called.
+        method: String,
+        /// Block the method was called for.
+        number: u64,
+        /// Error returned by the node.
+        message: String,
+    },
+
+    /// The result of a method could not be decoded.
+    #[error("invalid response to `{method}` for block {number}: {reason}")]
+    InvalidResponse {
+        /// Method called.
+        method: String,
+        /// Block the method was called for.
+        number: u64,
+        /// Why the result could not be decoded.
+        reason: String,
+    },
+
+    /// The node returned another block than the one asked for.
+    #[error("`{RAW_BLOCK_METHOD}` returned block {got} for block {number}")]
+    BlockMismatch {
+        /// Block asked for.
+        number: u64,
+        /// Block returned.
+        got: u64,
+    },
+
+    /// Serde JSON (de)serialization error, e.g., of the genesis file.
+    #[error("serde JSON (de)serialization error: {0}")]
+    Serde(#[from] serde_json::Error),
+}
+
+/// A source of responses to the methods a node serves for its blocks.
+pub trait Rpc {
+    /// Returns the result of calling `method` with block `number`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RpcDumpError::Missing` if no response is available, and `RpcDumpError::Rpc` if
+    /// the response is an error.
+    fn call(&self, method: &str, number: u64) -> Result<Value, RpcDumpError>;
+}
+
+/// The responses recorded in a dump directory.
+#[derive(Debug, Clone)]
+pub struct RecordedRpc {
+    dir: PathBuf,
+}
+
+impl RecordedRpc {
+    /// Serves the responses recorded in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Returns the blocks with recorded responses, in ascending order.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RpcDumpError::Io` if the directory cannot be read.
+    pub fn block_numbers(&self) -> Result<Vec<u64>, RpcDumpError> {
+        let mut numbers: Vec<u64> = read_dir(&self.dir)?
+            .into_iter()
+            .filter(|path| path.is_dir())
+            .filter_map(|path| path.file_name()?.to_str()?.parse().ok())
+            .collect();
+        numbers.sort_unstable();
+        Ok(numbers)
+    }
+
+    /// Reads the genesis file of the dump.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RpcDumpError::Io` if the file cannot be read, and `RpcDumpError::Serde` if it
+    /// cannot be parsed.
+    pub fn genesis(&self) -> Result<Genesis, RpcDumpError> {
+        let path = self.dir.join(GENESIS_FILE_NAME);
+        let contents =
+            fs::read_to_string(&path).map_err(|source| RpcDumpError::Io { path, source })?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+}
+
+impl Rpc for RecordedRpc {
+    fn call(&self, method: &str, number: u64) -> Result<Value, RpcDumpError> {
+        let path = self
+            .dir
+            .join(number.to_string())
+            .join(format!("{method}.json"));
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Err(RpcDumpError::Missing {
+                    method: method.to_string(),
+                    number,
+                });
+            }
+            Err(source) => return Err(RpcDumpError::Io { path, source }),
+        };
+        let response =
+            serde_json::from_str(&contents).map_err(|err| RpcDumpError::InvalidResponse {
+                method: method.to_string(),
+                number,
+                reason: err.to_string(),
+            })?;
+        result_of(method, number, response)
+    }
+}
+
+/// Responses held in memory, standing in for a node.
+#[derive(Debug, Clone, Default)]
+pub struct FakeRpc {
+    responses: BTreeMap<(String, u64), Value>,
+}
+
+impl FakeRpc {
+    /// Creates a stand-in without any response.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answers `method` for block `number` with `response`: either a whole JSON-RPC response,
+    /// or only its `result`.
+    pub fn insert(&mut self, method: &str, number: u64, response: Value) {
+        self.responses
+            .insert((method.to_string(), number), response);
+    }
+
+    /// Answers `debug_getRawBlock` for `block` with its RLP encoding, and
+    /// `debug_executionWitness` with `witness`, as a node would.
+    pub fn insert_block(&mut self, block: &Block, witness: &ExecutionWitness) {
+        let number = block.header.number;
+        let raw = Bytes::from(alloy_rlp::encode(block));
+        self.insert(RAW_BLOCK_METHOD, number, serde_json::json!(raw));
+        self.insert(
+            EXECUTION_WITNESS_METHOD,
+            number,
+            serde_json::to_value(witness).expect("execution witness should serialize"),
+        );
+    }
+}
+
+impl Rpc for FakeRpc {
+    fn call(&self, method: &str, number: u64) -> Result<Value, RpcDumpError> {
+        let response = self
+            .responses
+            .get(&(method.to_string(), number))
+            .cloned()
+            .ok_or_else(|| RpcDumpError::Missing {
+                method: method.to_string(),
+                number,
+            })?;
+        result_of(method, number, response)
+    }
+}
+
+/// Fetches block `number` and its execution witness from `rpc`.
+///
+/// # Errors
+///
+/// Returns an error if either response is missing or cannot be decoded, or if the block is not
+/// block `number`.
+pub fn client_input(rpc: &impl Rpc, number: u64) -> Result<ClientInput, RpcDumpError> {
+    let invalid = |method: &str, reason: String| RpcDumpError::InvalidResponse {
+        method: method.to_string(),
+        number,
+        reason,
+    };
+
+    let raw: Bytes = serde_json::from_value(rpc.call(RAW_BLOCK_METHOD, number)?)
+        .map_err(|err| invalid(RAW_BLOCK_METHOD, err.to_string()))?;
+    let block = Block::decode(&mut raw.as_ref())
+        .map_err(|err| invalid(RAW_BLOCK_METHOD, err.to_string()))?;
+    if block.header.number != number {
+        return Err(RpcDumpError::BlockMismatch {
+            number,
+            got: block.header.number,
+        });
+    }
+
+    let witness = serde_json::from_value(rpc.call(EXECUTION_WITNESS_METHOD, number)?)
+        .map_err(|err| invalid(EXECUTION_WITNESS_METHOD, err.to_string()))?;
+    Ok(ClientInput { block, witness })
+}
+
+/// Imports every block of the dump in `dir`, as a test case named `name`.
+///
+/// # Errors
+///
+/// Returns an error if the genesis file or the responses for a block cannot be read or decoded.
+pub fn import(name: impl Into<String>, dir: &Path) -> Result<BlocksAndWitnesses, RpcDumpError> {
+    let rpc = RecordedRpc::new(dir);
+    let blocks_and_witnesses = rpc
+        .block_numbers()?
+        .into_iter()
+        .map(|number| client_input(&rpc, number))
+        .collect::<Result<_, _>>()?;
+    Ok(BlocksAndWitnesses {
+        name: name.into(),
+        blocks_and_witnesses,
+        network: rpc.genesis()?,
+        expected_exception: None,
+    })
+}
+
+/// Imports each dump found directly within `dir`, i.e., each subdirectory holding a
+/// `genesis.json`, as a test case named after the subdirectory.
+///
+/// # Errors
+///
+/// Returns the first error [`import`] returns.
+pub fn import_all(dir: &Path) -> Result<Vec<BlocksAndWitnesses>, RpcDumpError> {
+    let mut dumps: Vec<PathBuf> = read_dir(dir)?
+        .into_iter()
+        .filter(|path| path.join(GENESIS_FILE_NAME).is_file())
+        .collect();
+    dumps.sort();
+    dumps
+        .iter()
+        .map(|dump| {
+            let name = dump
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            import(name, dump)
+        })
+        .collect()
+}
+
+/// Returns the `result` of a JSON-RPC `response` to `method`, or `response` itself if it is
+/// only the result.
+fn result_of(method: &str, number: u64, mut response: Value) -> Result<Value, RpcDumpError> {
+    let Some(envelope) = response
+        .as_object_mut()
+        .filter(|object| object.contains_key("jsonrpc"))
+    else {
+        return Ok(response);
+    };
+    if let Some(error) = envelope.remove("error") {
+        return Err(RpcDumpError::Rpc {
+            method: method.to_string(),
+            number,
+            message: error
+                .get("message")
+                .and_then(Value::as_str)
+                .map_or_else(|| error.to_string(), str::to_string),
+        });
+    }
+    envelope
+        .remove("result")
+        .ok_or_else(|| RpcDumpError::InvalidResponse {
+            method: method.to_string(),
+            number,
+            reason: "neither a result nor an error".to_string(),
+        })
+}
+
+/// Paths of the entries of `dir`.
+fn read_dir(dir: &Path) -> Result<Vec<PathBuf>, RpcDumpError> {
+    let io_error = |source| RpcDumpError::Io {
+        path: dir.to_path_buf(),
+        source,
+    };
+    fs::read_dir(dir)
+        .map_err(io_error)?
+        .map(|entry| entry.map(|entry| entry.path()).map_err(io_error))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn block(number: u64) -> Block {
+        let mut block = Block::default();
+        block.header.number = number;
+        block.header.gas_limit = 30_000_000;
+        block
+    }
+
+    fn witness() -> ExecutionWitness {
+        ExecutionWitness {
+            state: vec![Bytes::from_static(&[0xc0])],
+            codes: vec![Bytes::from_static(&[0x60, 0x00])],
+            keys: Vec::new(),
+            headers: vec![Bytes::from_static(&[0xc1, 0x80])],
+        }
+    }
+
+    #[test]
+    fn blocks_and_witnesses_round_trip_through_the_fake_rpc() {
+        let (block, witness) = (block(7), witness());
+        let mut rpc = FakeRpc::new();
+        rpc.insert_block(&block, &witness);
+
+        let input = client_input(&rpc, 7).unwrap();
+        assert_eq!(input.block.header.hash_slow(), block.header.hash_slow());
+        assert_eq!(input.witness, witness);
+    }
+
+    #[test]
+    fn recorded_responses_are_read_from_a_dump() {
+        let dir = tempfile::tempdir().unwrap();
+        let (block, witness) = (block(3), witness());
+        let mut responses = FakeRpc::new();
+        responses.insert_block(&block, &witness);
+        fs::create_dir(dir.path().join("3")).unwrap();
+        for method in [RAW_BLOCK_METHOD, EXECUTION_WITNESS_METHOD] {
+            let response = json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": responses.call(method, 3).unwrap(),
+            });
+            fs::write(
+                dir.path().join("3").join(format!("{method}.json")),
+                response.to_string(),
+            )
+            .unwrap();
+        }
+
+        let rpc = RecordedRpc::new(dir.path());
+        assert_eq!(rpc.block_numbers().unwrap(), [3]);
+        assert_eq!(client_input(&rpc, 3).unwrap().witness, witness);
+        assert!(matches!(
+            rpc.call(RAW_BLOCK_METHOD, 4),
+            Err(RpcDumpError::Missing { number: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn results_are_unwrapped_from_their_envelope() {
+        let response = json!({"jsonrpc": "2.0", "id": 1, "result": "0x01"});
+        assert_eq!(
+            result_of(RAW_BLOCK_METHOD, 1, response).unwrap(),
+            json!("0x01")
+        );
+        // A bare result is returned as is, even if it is an object.
+        let result = json!({"state": []});
+        assert_eq!(
+            result_of(EXECUTION_WITNESS_METHOD, 1, result.clone()).unwrap(),
+            result
+        );
+    }
+
+    #[test]
+    fn error_responses_are_errors() {
+        let mut rpc = FakeRpc::new();
+        rpc.insert(
+            RAW_BLOCK_METHOD,
+            1,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "error": {"code": -32000, "message": "block not found"},
+            }),
+        );
+        rpc.insert(
+            EXECUTION_WITNESS_METHOD,
+            1,
+            json!({"jsonrpc": "2.0", "id": 1, "error": "unavailable"}),
+        );
+
+        let err = rpc.call(RAW_BLOCK_METHOD, 1).unwrap_err();
+        assert!(
+            matches!(err, RpcDumpError::Rpc { ref message, .. } if message == "block not found"),
+            "{err}"
+        );
+        // Errors without a message are kept whole.
+        let err = rpc.call(EXECUTION_WITNESS_METHOD, 1).unwrap_err();
+        assert!(
+            matches!(err, RpcDumpError::Rpc { ref message, .. } if message == r#""unavailable""#),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn malformed_responses_are_invalid() {
+        let mut rpc = FakeRpc::new();
+        // An envelope with neither a result nor an error.
+        rpc.insert(RAW_BLOCK_METHOD, 1, json!({"jsonrpc": "2.0", "id": 1}));
+        // A result that is not hex.
+        rpc.insert(RAW_BLOCK_METHOD, 2, json!("not hex"));
+        // Bytes that are not a block.
+        rpc.insert(RAW_BLOCK_METHOD, 3, json!("0x01"));
+        // A witness of the wrong shape, for a valid block.
+        rpc.insert_block(&block(4), &witness());
+        rpc.insert(EXECUTION_WITNESS_METHOD, 4, json!("not a witness"));
+
+        for number in 1..=4 {
+            let err = client_input(&rpc, number).unwrap_err();
+            assert!(
+                matches!(err, RpcDumpError::InvalidResponse { number: n, .. } if n == number),
+                "{err}"
+            );
+        }
+    }
+
+    #[test]
+    fn missing_and_mismatching_blocks_are_errors() {
+        let mut rpc = FakeRpc::new();
+        rpc.insert_block(&block(5), &witness());
+        rpc.insert(
+            RAW_BLOCK_METHOD,
+            6,
+            json!(Bytes::from(alloy_rlp::encode(block(5)))),
+        );
+
+        assert!(matches!(
+            client_input(&rpc, 8),
+            Err(RpcDumpError::Missing { number: 8, .. })
+        ));
+        assert!(matches!(
+            client_input(&rpc, 6),
+            Err(RpcDumpError::BlockMismatch { number: 6, got: 5 })
+        ));
+    }
+}
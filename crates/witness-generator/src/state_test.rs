@@ -0,0 +1,493 @@
+//! Execution-spec state tests, turned into blockchain tests of a single block.
+//!
+//! A state test applies one transaction to a pre-state, in the environment of a block, and lists
+//! the outcome for each fork (and each combination of the transaction's data, gas and value).
+//! `ef-tests` only runs blockchain tests, so each of these outcomes is turned into a blockchain
+//! test whose genesis block holds the pre-state, followed by a single block holding the
+//! transaction.
+//!
+//! A state test does not record what the header of that block commits to (gas used, receipts,
+//! post-state root, ...). It is filled by executing the block once beforehand, with the block
+//! executor `ef-tests` uses.
+
+use std::{collections::BTreeMap, path::Path, slice, sync::Arc};
+
+use alloy_consensus::{
+    Header, Transaction, constants::EMPTY_ROOT_HASH, proofs::calculate_transaction_root,
+};
+use alloy_eips::{
+    eip1559::DEFAULT_ELASTICITY_MULTIPLIER,
+    eip2718::Decodable2718,
+    eip2935::{HISTORY_STORAGE_ADDRESS, HISTORY_STORAGE_CODE},
+    eip4788::{BEACON_ROOTS_ADDRESS, BEACON_ROOTS_CODE},
+    eip4844::DATA_GAS_PER_BLOB,
+    eip4895::Withdrawals,
+    eip7002::{WITHDRAWAL_REQUEST_PREDEPLOY_ADDRESS, WITHDRAWAL_REQUEST_PREDEPLOY_CODE},
+    eip7251::{CONSOLIDATION_REQUEST_PREDEPLOY_ADDRESS, CONSOLIDATION_REQUEST_PREDEPLOY_CODE},
+    eip7685::EMPTY_REQUESTS_HASH,
+};
+use alloy_primitives::{Address, B256, Bytes, U256};
+use ef_tests::models::{BlockchainTest, ForkSpec};
+use reth_chainspec::{ChainSpec, EthChainSpec, EthereumHardforks};
+use reth_ethereum_primitives::{Block, BlockBody, TransactionSigned};
+use reth_primitives_traits::Block as _;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::in_memory_state::InMemoryState;
+
+/// A state test, as found in a JSON file of `zkevm-fixtures/fixtures/state_tests`.
+///
+/// Fields `ef-tests` does not need to build the block (the transaction in its unsigned form,
+/// `config`, `_info`, ...) are ignored.
+#[derive(Debug, Deserialize)]
+pub(crate) struct StateTest {
+    /// Environment of the block the transaction is included in.
+    env: Env,
+    /// State the transaction is applied to.
+    pre: BTreeMap<Address, Account>,
+    /// Outcomes of the transaction, for each fork.
+    post: BTreeMap<String, Vec<PostState>>,
+}
+
+/// Environment of the block of a state test.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Env {
+    current_coinbase: Address,
+    current_gas_limit: U256,
+    current_number: U256,
+    current_timestamp: U256,
+    /// `prevrandao`, from Paris on.
+    #[serde(default)]
+    current_random: Option<B256>,
+    /// From London on.
+    #[serde(default)]
+    current_base_fee: Option<U256>,
+    /// From Cancun on.
+    #[serde(default)]
+    current_excess_blob_gas: Option<U256>,
+}
+
+/// An account of the pre-state, in the format blockchain tests use as well.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Account {
+    balance: U256,
+    nonce: U256,
+    #[serde(default)]
+    code: Bytes,
+    #[serde(default)]
+    storage: BTreeMap<U256, U256>,
+}
+
+/// An outcome of the transaction of a state test.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PostState {
+    /// The signed transaction, with the data, gas and value of `indexes`.
+    txbytes: Bytes,
+    indexes: Indexes,
+    /// Exception the transaction is expected to be rejected with.
+    #[serde(default)]
+    expect_exception: Option<String>,
+}
+
+/// Blockchain tests built from the outcomes of a state test.
+#[derive(Debug, Default)]
+pub(crate) struct BlockchainTests {
+    /// Blockchain tests, by name.
+    pub(crate) tests: Vec<(String, BlockchainTest)>,
+    /// Forks and outcomes no blockchain test could be built for, with the reason why.
+    pub(crate) skipped: Vec<(String, String)>,
+}
+
+/// Which of the data, gas and value of the transaction of a state test an outcome is for.
+#[derive(Debug, Deserialize)]
+struct Indexes {
+    data: usize,
+    gas: usize,
+    value: usize,
+}
+
+impl StateTest {
+    /// Loads the state tests of the JSON file at `path`, by name.
+    ///
+    /// # Panics
+    ///
+    /// If the file cannot be read or parsed.
+    pub(crate) fn load(path: &Path) -> BTreeMap<String, Self> {
+        let contents = std::fs::read_to_string(path)
+            .unwrap_or_else(|err| panic!("failed to read {}: {err}", path.display()));
+        serde_json::from_str(&contents)
+            .unwrap_or_else(|err| panic!("failed to parse {}: {err}", path.display()))
+    }
+
+    /// Turns each outcome of the state test into a blockchain test, named after the state test
+    /// `name`, and after the fork and indexes of the outcome unless it is the only one.
+    ///
+    /// Forks `ef-tests` does not know of, forks before Paris, whose blocks would need a
+    /// proof-of-work difficulty, and outcomes whose block cannot be built (e.g., a transaction
+    /// that does not decode) are skipped, and listed in [`BlockchainTests::skipped`].
+    pub(crate) fn blockchain_tests(&self, name: &str) -> BlockchainTests {
+        let outcomes = self.post.values().map(Vec::len).sum::<usize>();
+        let mut tests = BlockchainTests::default();
+        for (fork, posts) in &self.post {
+            let Ok(fork_spec) = serde_json::from_value::<ForkSpec>(json!(fork)) else {
+                tests
+                    .skipped
+                    .push((format!("{name}_{fork}"), "unknown fork".to_string()));
+                continue;
+            };
+            let chain_spec = Arc::new(ChainSpec::from(fork_spec));
+            if !chain_spec.is_paris_active_at_block(0) {
+                tests
+                    .skipped
+                    .push((format!("{name}_{fork}"), "fork before Paris".to_string()));
+                continue;
+            }
+            for post in posts {
+                let test_name = if outcomes == 1 {
+                    name.to_string()
+                } else {
+                    let Indexes { data, gas, value } = post.indexes;
+                    format!("{name}_{fork}_d{data}g{gas}v{value}")
+                };
+                match self.blockchain_test(fork, chain_spec.clone(), post) {
+                    Ok(test) => tests.tests.push((test_name, test)),
+                    Err(reason) => tests.skipped.push((test_name, reason)),
+                }
+            }
+        }
+        tests
+    }
+
+    /// Builds the blockchain test of outcome `post` on `fork`.
+    ///
+    /// Returns why the test cannot be built if the fixture is malformed, or the transaction
+    /// fails to execute although it is not expected to be rejected.
+    fn blockchain_test(
+        &self,
+        fork: &str,
+        chain_spec: Arc<ChainSpec>,
+        post: &PostState,
+    ) -> Result<BlockchainTest, String> {
+        let env = &self.env;
+        let number: u64 = env.current_number.to();
+        let timestamp: u64 = env.current_timestamp.to();
+        let gas_limit: u64 = env.current_gas_limit.to();
+        let shanghai = chain_spec.is_shanghai_active_at_timestamp(timestamp);
+        let cancun = chain_spec.is_cancun_active_at_timestamp(timestamp);
+        let prague = chain_spec.is_prague_active_at_timestamp(timestamp);
+
+        let pre = with_system_contracts(self.pre.clone(), &chain_spec, timestamp);
+        let mut state = InMemoryState::default();
+        for (address, account) in &pre {
+            state.insert_account(
+                *address,
+                account.balance,
+                account.nonce.to(),
+                account.code.clone(),
+                account.storage.clone(),
+            );
+        }
+
+        // The base fee and excess blob gas of a block follow from those of its parent: the parent
+        // keeps them unchanged by using exactly its target gas and blob gas.
+        let excess_blob_gas = cancun.then(|| env.current_excess_blob_gas.unwrap_or_default().to());
+        let genesis = Header {
+            number: number
+                .checked_sub(1)
+                .ok_or("the block of the state test is the genesis block")?,
+            gas_limit,
+            gas_used: gas_limit / DEFAULT_ELASTICITY_MULTIPLIER,
+            base_fee_per_gas: env.current_base_fee.map(|fee| fee.to()),
+            withdrawals_root: shanghai.then_some(EMPTY_ROOT_HASH),
+            blob_gas_used: chain_spec
+                .blob_params_at_timestamp(timestamp)
+                .filter(|_| cancun)
+                .map(|params| params.target_blob_count * DATA_GAS_PER_BLOB),
+            excess_blob_gas,
+            parent_beacon_block_root: cancun.then_some(B256::ZERO),
+            requests_hash: prague.then_some(EMPTY_REQUESTS_HASH),
+            state_root: state.state_root(),
+            ..Default::default()
+        };
+        let genesis_hash = genesis.hash_slow();
+
+        let tx = TransactionSigned::decode_2718(&mut post.txbytes.as_ref())
+            .map_err(|err| format!("invalid transaction: {err}"))?;
+        let header = Header {
+            parent_hash: genesis_hash,
+            beneficiary: env.current_coinbase,
+            // A block the transaction is rejected from keeps the pre-state.
+            state_root: genesis.state_root,
+            transactions_root: calculate_transaction_root(slice::from_ref(&tx)),
+            number,
+            gas_limit,
+            timestamp,
+            mix_hash: env.current_random.unwrap_or_default(),
+            base_fee_per_gas: genesis.base_fee_per_gas,
+            withdrawals_root: genesis.withdrawals_root,
+            blob_gas_used: cancun.then(|| tx.blob_gas_used().unwrap_or_default()),
+            excess_blob_gas,
+            parent_beacon_block_root: genesis.parent_beacon_block_root,
+            requests_hash: genesis.requests_hash,
+            ..Default::default()
+        };
+        let mut block = Block {
+            header,
+            body: BlockBody {
+                transactions: vec![tx],
+                ommers: Vec::new(),
+                withdrawals: shanghai.then(Withdrawals::default),
+            },
+        };
+
+        if post.expect_exception.is_none() {
+            let recovered = block
+                .clone()
+                .try_into_recovered()
+                .map_err(|_| "failed to recover the transaction signer")?;
+            let execution = state
+                .execute(chain_spec, &recovered, [(genesis.number, genesis_hash)])
+                .map_err(|err| format!("failed to execute the block: {err}"))?;
+            state.commit(&execution.bundle);
+            execution.complete_header(&mut block.header);
+            block.header.state_root = state.state_root();
+        }
+
+        let last_block_hash = if post.expect_exception.is_some() {
+            genesis_hash
+        } else {
+            block.header.hash_slow()
+        };
+        serde_json::from_value(json!({
+            "genesisBlockHeader": ef_tests_header(&genesis, genesis_hash),
+            "blocks": [{
+                "rlp": Bytes::from(alloy_rlp::encode(&block)),
+                "expectException": post.expect_exception,
+            }],
+            "pre": pre,
+            "lastblockhash": last_block_hash,
+            "network": fork,
+            "sealEngine": "NoProof",
+        }))
+        .map_err(|err| format!("invalid blockchain test: {err}"))
+    }
+}
+
+/// Adds the system contracts active on `chain_spec` at `timestamp` to `pre`, unless the state
+/// test deploys them itself.
+///
+/// Blocks call them before and after their transactions, whereas a state test only runs its
+/// transaction, so its pre-state usually lacks them.
+fn with_system_contracts(
+    mut pre: BTreeMap<Address, Account>,
+    chain_spec: &ChainSpec,
+    timestamp: u64,
+) -> BTreeMap<Address, Account> {
+    let mut contracts = Vec::new();
+    if chain_spec.is_cancun_active_at_timestamp(timestamp) {
+        contracts.push((BEACON_ROOTS_ADDRESS, &BEACON_ROOTS_CODE));
+    }
+    if chain_spec.is_prague_active_at_timestamp(timestamp) {
+        contracts.extend([
+            (HISTORY_STORAGE_ADDRESS, &HISTORY_STORAGE_CODE),
+            (
+                WITHDRAWAL_REQUEST_PREDEPLOY_ADDRESS,
+                &WITHDRAWAL_REQUEST_PREDEPLOY_CODE,
+            ),
+            (
+                CONSOLIDATION_REQUEST_PREDEPLOY_ADDRESS,
+                &CONSOLIDATION_REQUEST_PREDEPLOY_CODE,
+            ),
+        ]);
+    }
+    for (address, code) in contracts {
+        pre.entry(address).or_insert_with(|| Account {
+            nonce: U256::from(1),
+            code: code.clone(),
+            ..Default::default()
+        });
+    }
+    pre
+}
+
+/// `header`, in the format of the headers of blockchain tests.
+fn ef_tests_header(header: &Header, hash: B256) -> serde_json::Value {
+    json!({
+        "parentHash": header.parent_hash,
+        "uncleHash": header.ommers_hash,
+        "coinbase": header.beneficiary,
+        "stateRoot": header.state_root,
+        "transactionsTrie": header.transactions_root,
+        "receiptTrie": header.receipts_root,
+        "bloom": header.logs_bloom,
+        "difficulty": header.difficulty,
+        "number": U256::from(header.number),
+        "gasLimit": U256::from(header.gas_limit),
+        "gasUsed": U256::from(header.gas_used),
+        "timestamp": U256::from(header.timestamp),
+        "extraData": header.extra_data,
+        "mixHash": header.mix_hash,
+        "nonce": header.nonce,
+        "baseFeePerGas": header.base_fee_per_gas.map(U256::from),
+        "withdrawalsRoot": header.withdrawals_root,
+        "blobGasUsed": header.blob_gas_used.map(U256::from),
+        "excessBlobGas": header.excess_blob_gas.map(U256::from),
+        "parentBeaconBlockRoot": header.parent_beacon_block_root,
+        "requestsHash": header.requests_hash,
+        "hash": hash,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_consensus::{SignableTransaction, TxLegacy};
+    use alloy_eips::eip2718::Encodable2718;
+    use alloy_primitives::{TxKind, address};
+    use alloy_rlp::Decodable;
+    use alloy_signer::SignerSync;
+    use alloy_signer_local::PrivateKeySigner;
+
+    use super::*;
+    use crate::generate_stateless_witness::run_case_keeping_invalid_block;
+
+    const RECIPIENT: Address = address!("0x00000000000000000000000000000000000000aa");
+    const INTRINSIC_GAS_TOO_LOW: &str = "TransactionException.INTRINSIC_GAS_TOO_LOW";
+
+    /// A transfer of one wei, signed by `signer`.
+    fn transfer(signer: &PrivateKeySigner, gas_limit: u64) -> Bytes {
+        let tx = TxLegacy {
+            chain_id: Some(1),
+            nonce: 0,
+            gas_price: 10,
+            gas_limit,
+            to: TxKind::Call(RECIPIENT),
+            value: U256::from(1),
+            input: Bytes::new(),
+        };
+        let signature = signer.sign_hash_sync(&tx.signature_hash()).unwrap();
+        tx.into_signed(signature).encoded_2718().into()
+    }
+
+    /// A state test of a transfer with two gas limits, the second of which is too low, on
+    /// Cancun, on a fork before Paris and on a fork `ef-tests` does not know of.
+    fn state_test() -> StateTest {
+        let signer = PrivateKeySigner::from_bytes(&B256::repeat_byte(0x11)).unwrap();
+        let post = json!([
+            {
+                "txbytes": transfer(&signer, 21_000),
+                "indexes": {"data": 0, "gas": 0, "value": 0},
+            },
+            {
+                "txbytes": transfer(&signer, 20_000),
+                "indexes": {"data": 0, "gas": 1, "value": 0},
+                "expectException": INTRINSIC_GAS_TOO_LOW,
+            },
+        ]);
+        serde_json::from_value(json!({
+            "env": {
+                "currentCoinbase": "0x00000000000000000000000000000000000000cc",
+                "currentGasLimit": "0x01c9c380",
+                "currentNumber": "0x01",
+                "currentTimestamp": "0x03e8",
+                "currentRandom": B256::ZERO,
+                "currentBaseFee": "0x0a",
+                "currentExcessBlobGas": "0x00",
+            },
+            "pre": {
+                signer.address().to_string(): {
+                    "balance": "0x0de0b6b3a7640000",
+                    "nonce": "0x00",
+                },
+            },
+            "post": {
+                "Cancun": post,
+                "Frontier": post,
+                "Osaka2": post,
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn outcomes_become_single_block_tests() {
+        let tests = state_test().blockchain_tests("transfer");
+        let names: Vec<_> = tests.tests.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["transfer_Cancun_d0g0v0", "transfer_Cancun_d0g1v0"]);
+        assert_eq!(
+            tests.skipped,
+            [
+                (
+                    "transfer_Frontier".to_string(),
+                    "fork before Paris".to_string()
+                ),
+                ("transfer_Osaka2".to_string(), "unknown fork".to_string()),
+            ]
+        );
+
+        let (_, accepted) = &tests.tests[0];
+        let [block] = accepted.blocks.as_slice() else {
+            panic!("expected a single block");
+        };
+        assert_eq!(block.expect_exception, None);
+        let block = Block::decode(&mut block.rlp.as_ref()).unwrap();
+        let header = &block.header;
+        assert_eq!(header.number, 1);
+        assert_eq!(header.parent_hash, accepted.genesis_block_header.hash);
+        assert_eq!(header.gas_used, 21_000);
+        assert_eq!(header.base_fee_per_gas, Some(10));
+        assert_eq!(header.blob_gas_used, Some(0));
+        assert_eq!(header.parent_beacon_block_root, Some(B256::ZERO));
+        assert_ne!(header.state_root, accepted.genesis_block_header.state_root);
+        assert_eq!(accepted.lastblockhash, header.hash_slow());
+
+        let (client_inputs, expected_exception) = run_case_keeping_invalid_block(accepted).unwrap();
+        assert_eq!(expected_exception, None);
+        let [client_input] = client_inputs.as_slice() else {
+            panic!("expected a single block");
+        };
+        assert_eq!(client_input.block.header.hash_slow(), header.hash_slow());
+        assert!(!client_input.witness.state.is_empty());
+    }
+
+    #[test]
+    fn rejected_outcomes_keep_their_block() {
+        let tests = state_test().blockchain_tests("transfer");
+        let (_, rejected) = &tests.tests[1];
+        assert_eq!(
+            rejected.blocks[0].expect_exception.as_deref(),
+            Some(INTRINSIC_GAS_TOO_LOW)
+        );
+        // The block keeps the pre-state, and the chain ends at the genesis block.
+        assert_eq!(rejected.lastblockhash, rejected.genesis_block_header.hash);
+
+        let (client_inputs, expected_exception) = run_case_keeping_invalid_block(rejected).unwrap();
+        assert_eq!(expected_exception.as_deref(), Some(INTRINSIC_GAS_TOO_LOW));
+        assert_eq!(client_inputs.len(), 1);
+    }
+
+    #[test]
+    fn unexpected_acceptance_is_an_error() {
+        let mut tests = state_test().blockchain_tests("transfer");
+        let (_, mut accepted) = tests.tests.swap_remove(0);
+        accepted.blocks[0].expect_exception = Some(INTRINSIC_GAS_TOO_LOW.to_string());
+
+        let err = run_case_keeping_invalid_block(&accepted).unwrap_err();
+        assert_eq!(
+            err,
+            format!("block 0 was expected to fail with {INTRINSIC_GAS_TOO_LOW}, but was accepted")
+        );
+    }
+
+    #[test]
+    fn a_single_outcome_keeps_the_name_of_the_state_test() {
+        let mut state_test = state_test();
+        state_test.post.retain(|fork, _| fork == "Cancun");
+        state_test.post.get_mut("Cancun").unwrap().truncate(1);
+        let tests = state_test.blockchain_tests("transfer");
+        assert_eq!(tests.tests[0].0, "transfer");
+        assert!(tests.skipped.is_empty());
+    }
+}
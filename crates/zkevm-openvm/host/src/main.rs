@@ -1,57 +1,119 @@
 //! Host program for Ethereum validation
 
+use alloy_genesis::Genesis;
+use benchmark_runner::{BlockExecution, GuestOutput, MAX_REJECTION_LEN, run_benchmark};
 use eyre::Result;
 use openvm_build::GuestOptions;
-use openvm_sdk::{Sdk, StdIn, config::SdkVmConfig};
+use openvm_circuit::arch::SystemConfig;
+use openvm_sdk::{F, Sdk, StdIn, config::SdkVmConfig};
+use openvm_stark_sdk::openvm_stark_backend::p3_field::PrimeField32;
 use openvm_transpiler::elf::Elf;
-use std::path::Path;
+use serde::de::DeserializeOwned;
+use std::{collections::HashMap, path::Path};
+use witness_generator::ClientInput;
 
-use generate_stateless_witness::generate;
-use std::collections::HashMap;
-use witness_generator::generate_stateless_witness;
+/// Bytes of the length prefix the guest reveals before its output.
+const LENGTH_PREFIX_LEN: usize = 4;
+
+/// Upper bound on the number of distinct opcodes in an execution profile.
+const MAX_PROFILED_OPCODES: usize = 256;
+
+/// Upper bound on the length of an opcode mnemonic in an execution profile.
+const MAX_OPCODE_NAME_LEN: usize = 16;
+
+/// Upper bound on the number of distinct precompiles in an execution profile.
+const MAX_PROFILED_PRECOMPILES: usize = 32;
 
 fn main() -> Result<()> {
     let sdk = Sdk::new();
-    let vm_cfg = SdkVmConfig::builder()
-        .system(Default::default())
-        .rv32i(Default::default())
-        .rv32m(Default::default())
-        .io(Default::default())
-        .build();
 
     // Build the guest crate
     let guest_path = Path::new("../program");
     let elf: Elf = sdk.build(GuestOptions::default(), guest_path, &Default::default())?;
 
-    // Transpile and execute
-    let exe = sdk.transpile(elf, vm_cfg.transpiler())?;
+    // Transpile once: the number of public values does not change the program.
+    let exe = sdk.transpile(elf, vm_config(0).transpiler())?;
 
-    let generated = generate();
-    let num_corpuses = generated.len();
+    run_benchmark("openvm", |client_input: &ClientInput, network: &Genesis| {
+        let mut stdin = StdIn::default();
+        stdin.write(client_input);
+        stdin.write(network);
 
-    for (corpus_id, blockchain_corpus) in generated.into_iter().enumerate() {
-        // Iterate each block in the mini blockchain
-        let name = blockchain_corpus.name.clone();
-        let num_blocks_in_corpus = blockchain_corpus.blocks_and_witnesses.len();
+        let num_public_values = num_public_values(client_input.block.body.transactions.len());
+        // A failure of the VM or of decoding is not a rejection of the block by the guest,
+        // so it must not end up in the guest output.
+        let public_values = sdk
+            .execute(exe.clone(), vm_config(num_public_values), stdin)
+            .unwrap_or_else(|err| panic!("OpenVM execution failed: {err}"));
+        let output = decode_public_values::<GuestOutput>(&public_values)
+            .unwrap_or_else(|err| panic!("failed to decode the guest output: {err}"));
 
-        println!("Processing corpus {} / {num_corpuses}", corpus_id + 1);
-        println!("Corpus name: {name}");
-        println!("Num blocks in corpus: {num_blocks_in_corpus}\n");
+        BlockExecution {
+            // The SDK's execute does not report cycle counts, and the guest does not mark
+            // regions on OpenVM.
+            total_num_cycles: 0,
+            region_cycles: HashMap::new(),
+            cycles_unsupported: true,
+            output,
+            execution_report: None,
+        }
+    });
 
-        for (block_id, client_input) in blockchain_corpus.blocks_and_witnesses.iter().enumerate() {
-            println!(
-                "   Processing block {} / {num_blocks_in_corpus}",
-                block_id + 1
-            );
+    Ok(())
+}
 
-            let block_number = client_input.block.number;
-            let mut stdin = StdIn::default();
-            stdin.write(client_input);
-            stdin.write(&blockchain_corpus.network);
+/// Configuration of the VM, revealing `num_public_values` bytes.
+fn vm_config(num_public_values: usize) -> SdkVmConfig {
+    SdkVmConfig::builder()
+        .system(
+            SystemConfig::default()
+                .with_public_values(num_public_values)
+                .into(),
+        )
+        .rv32i(Default::default())
+        .rv32m(Default::default())
+        .io(Default::default())
+        .build()
+}
 
-            let outputs = sdk.execute(exe.clone(), vm_cfg.clone(), stdin)?;
-        }
-    }
+/// Number of public value bytes the guest may reveal for a block with `num_transactions`
+/// transactions.
+///
+/// Bounds the length prefix and the bincode-encoded guest output, which grows with the number of
+/// transactions and, in profiling builds, with the execution profile; or, for rejected blocks,
+/// holds the validation error, which the guest truncates to [`MAX_REJECTION_LEN`] bytes. Rounded
+/// up to whole words, as the guest reveals `u32`s.
+fn num_public_values(num_transactions: usize) -> usize {
+    // Each `Vec`, `String` and map is prefixed with its `u64` length, an `Option` with a byte and
+    // a `Result` with a `u32` variant tag.
+    let profile = 1
+        + 8
+        + MAX_PROFILED_OPCODES * (8 + MAX_OPCODE_NAME_LEN + 8)
+        + 8
+        + MAX_PROFILED_PRECOMPILES * (20 + 8);
+    let accepted = 3 * 32 + 8 + 8 * num_transactions + profile;
+    let rejected = 8 + MAX_REJECTION_LEN;
+    (LENGTH_PREFIX_LEN + 4 + accepted.max(rejected)).next_multiple_of(4)
+}
 
-    Ok(())
+/// Decodes a value revealed by the guest.
+///
+/// Each public value holds one byte; the guest reveals a little-endian `u32` length prefix
+/// followed by the bincode-encoded value.
+fn decode_public_values<T: DeserializeOwned>(public_values: &[F]) -> Result<T, String> {
+    let bytes: Vec<u8> = public_values
+        .iter()
+        .map(|value| value.as_canonical_u32() as u8)
+        .collect();
+    let (prefix, encoded) = bytes
+        .split_first_chunk::<LENGTH_PREFIX_LEN>()
+        .ok_or_else(|| format!("{} public values hold no length prefix", bytes.len()))?;
+    let len = u32::from_le_bytes(*prefix) as usize;
+    let encoded = encoded.get(..len).ok_or_else(|| {
+        format!(
+            "public values hold {} bytes, but the guest revealed {len}",
+            encoded.len()
+        )
+    })?;
+    bincode::deserialize(encoded).map_err(|err| format!("invalid public values: {err}"))
 }
@@ -1,10 +1,8 @@
-use std::collections::HashMap;
-
-use benchmark_runner::run_benchmark;
+use alloy_genesis::Genesis;
+use benchmark_runner::{parse_region_reports, run_benchmark, BlockExecution, GuestOutput};
 use methods::RISC0_GUEST_ELF;
 use risc0_zkvm::{default_prover, ExecutorEnv};
-use witness_generator::BlocksAndWitnesses;
-use zkevm_metrics::WorkloadMetrics;
+use witness_generator::ClientInput;
 
 fn main() {
     // Initialize tracing. In order to view logs, run `RUST_LOG=info cargo run`
@@ -12,41 +10,34 @@ fn main() {
         .with_env_filter(tracing_subscriber::filter::EnvFilter::from_default_env())
         .init();
 
-    run_benchmark(
-        RISC0_GUEST_ELF,
-        "risc0",
-        |blockchain_corpus: &BlocksAndWitnesses, _elf_data: &'static [u8]| {
-            // Obtain the default prover.
-            let prover = default_prover();
-            let mut reports = Vec::new();
-            let corpus_name = &blockchain_corpus.name;
-
-            for client_input in &blockchain_corpus.blocks_and_witnesses {
-                let block_number = client_input.block.number;
-
-                let env = ExecutorEnv::builder()
-                    .write(&client_input)
-                    .unwrap()
-                    .write(&blockchain_corpus.network)
-                    .unwrap()
-                    .build()
-                    .unwrap();
-
-                // Proof information by proving the specified ELF binary.
-                let _ = prover.prove(env, RISC0_GUEST_ELF).unwrap();
-
-                // RISC0 receipt does not provide detailed region cycle counts by default.
-                // We'll use an empty HashMap for region_cycles.
-                let region_cycles: HashMap<String, u64> = HashMap::new();
-
-                let metrics = WorkloadMetrics {
-                    name: format!("{}-{}", corpus_name, block_number),
-                    total_num_cycles: 0, // TODO
-                    region_cycles,
-                };
-                reports.push(metrics);
-            }
-            reports
-        },
-    );
+    run_benchmark("risc0", |client_input: &ClientInput, network: &Genesis| {
+        // Obtain the default prover.
+        let prover = default_prover();
+
+        // The guest reports its region cycles on stderr.
+        let mut guest_stderr = Vec::new();
+        let env = ExecutorEnv::builder()
+            .write(client_input)
+            .unwrap()
+            .write(network)
+            .unwrap()
+            .stderr(&mut guest_stderr)
+            .build()
+            .unwrap();
+
+        // Proof information by proving the specified ELF binary.
+        let prove_info = prover.prove(env, RISC0_GUEST_ELF).unwrap();
+        let output: GuestOutput = prove_info.receipt.journal.decode().unwrap();
+
+        let region_cycles = parse_region_reports(&String::from_utf8_lossy(&guest_stderr));
+
+        BlockExecution {
+            // Every cycle of the session, paging and segment padding included.
+            total_num_cycles: prove_info.stats.total_cycles,
+            region_cycles,
+            cycles_unsupported: false,
+            output,
+            execution_report: None,
+        }
+    });
 }
@@ -1,18 +1,18 @@
 #![doc = include_str!("../../README.md")]
 
-use benchmark_runner::run_benchmark;
-use sp1_sdk::{ProverClient, SP1Stdin};
-use std::collections::HashMap;
-use witness_generator::BlocksAndWitnesses;
-use zkevm_metrics::WorkloadMetrics;
+use alloy_genesis::Genesis;
+use benchmark_runner::{BlockExecution, ExecutionReportMetrics, GuestOutput, run_benchmark};
+use sp1_sdk::{ExecutionReport, ProverClient, SP1Stdin};
+use witness_generator::ClientInput;
 
 /// Path to the compiled RISC-V ELF file for the `succinct-guest` crate.
 ///
 /// This constant assumes the ELF has been built using `cargo prove build --release`
-/// within the `crates/zkevm-succinct/succinct-guest` directory.
+/// within the `crates/zkevm-succinct/succinct-guest` directory, into the target
+/// directory of the `succinct` patch-set (`ZKEVM_TARGET_DIR`, set by xtask).
 pub const STATELESS_ELF: &[u8] = include_bytes!(concat!(
-    env!("CARGO_WORKSPACE_DIR"),
-    "/target/elf-compilation/riscv32im-succinct-zkvm-elf/release/succinct-guest"
+    env!("ZKEVM_TARGET_DIR"),
+    "/elf-compilation/riscv32im-succinct-zkvm-elf/release/succinct-guest"
 ));
 
 /// Main entry point for the host benchmarker.
@@ -28,31 +28,42 @@ fn main() {
     let client = ProverClient::from_env();
 
     run_benchmark(
-        STATELESS_ELF,
         "succinct",
-        |blockchain_corpus: &BlocksAndWitnesses, elf_path: &'static [u8]| {
-            let mut reports = Vec::new();
-            let name = &blockchain_corpus.name;
-
-            for client_input in &blockchain_corpus.blocks_and_witnesses {
-                let block_number = client_input.block.number;
-                let mut stdin = SP1Stdin::new();
-                stdin.write(client_input);
-                stdin.write(&blockchain_corpus.network);
-
-                let (_, report) = client.execute(elf_path, &stdin).run().unwrap();
-
-                let total_num_cycles = report.total_instruction_count();
-                let region_cycles: HashMap<_, _> = report.cycle_tracker.into_iter().collect();
-
-                let metrics = WorkloadMetrics {
-                    name: format!("{}-{}", name, block_number),
-                    total_num_cycles,
-                    region_cycles,
-                };
-                reports.push(metrics);
+        |client_input: &ClientInput, network: &Genesis| {
+            let mut stdin = SP1Stdin::new();
+            stdin.write(client_input);
+            stdin.write(network);
+
+            let (mut public_values, report) = client.execute(STATELESS_ELF, &stdin).run().unwrap();
+
+            BlockExecution {
+                total_num_cycles: report.total_instruction_count(),
+                execution_report: Some(execution_report_metrics(&report)),
+                region_cycles: report.cycle_tracker.into_iter().collect(),
+                cycles_unsupported: false,
+                output: public_values.read::<GuestOutput>(),
             }
-            reports
         },
     );
 }
+
+/// Extracts the instruction and syscall counts, touched memory and gas from an SP1
+/// `ExecutionReport`, leaving out the instructions and syscalls that never ran.
+fn execution_report_metrics(report: &ExecutionReport) -> ExecutionReportMetrics {
+    ExecutionReportMetrics {
+        opcode_counts: report
+            .opcode_counts
+            .iter()
+            .filter(|(_, count)| **count > 0)
+            .map(|(opcode, count)| (opcode.to_string(), *count))
+            .collect(),
+        syscall_counts: report
+            .syscall_counts
+            .iter()
+            .filter(|(_, count)| **count > 0)
+            .map(|(syscall, count)| (format!("{syscall:?}"), *count))
+            .collect(),
+        touched_memory_addresses: report.touched_memory_addresses,
+        gas: report.gas,
+    }
+}
@@ -1,17 +1,17 @@
 #![doc = include_str!("../../README.md")]
 
-use generate_stateless_witness::generate;
-use zkevm_metrics::WorkloadMetrics;
-use zkm_sdk::{ProverClient, ZKMStdin};
-
-use std::collections::HashMap;
-use witness_generator::BlocksAndWitnesses;
+use alloy_genesis::Genesis;
+use benchmark_runner::{BlockExecution, GuestOutput, run_benchmark};
+use witness_generator::ClientInput;
 use zkm_sdk::{ProverClient, ZKMStdin};
 
 /// Path to the compiled MIPS ELF file for the `zkm-guest` crate.
+///
+/// The ELF is built by `build.rs` into the target directory of the `zkm`
+/// patch-set (`ZKEVM_TARGET_DIR`, set by xtask).
 pub const STATELESS_ELF: &[u8] = include_bytes!(concat!(
-    env!("CARGO_WORKSPACE_DIR"),
-    "/target/mipsel-zkm-zkvm-elf/release/zkm-guest"
+    env!("ZKEVM_TARGET_DIR"),
+    "/mipsel-zkm-zkvm-elf/release/zkm-guest"
 ));
 
 /// Main entry point for the host benchmarker.
@@ -26,33 +26,19 @@ fn main() {
     // Setup the prover client.
     let client = ProverClient::cpu();
 
-    run_benchmark(
-        STATELESS_ELF,
-        "zkm",
-        |blockchain_corpus: &BlocksAndWitnesses, elf_path: &'static [u8]| {
-            let mut reports = Vec::new();
-            let name = &blockchain_corpus.name;
-            let num_blocks_in_corpus = blockchain_corpus.blocks_and_witnesses.len();
-
-            for client_input in &blockchain_corpus.blocks_and_witnesses {
-                let block_number = client_input.block.number;
-                let mut stdin = ZKMStdin::new();
-                stdin.write(client_input);
-                stdin.write(&blockchain_corpus.network);
-
-                let (_, report) = client.execute(elf_path, stdin).run().unwrap();
-
-                let total_num_cycles = report.total_instruction_count();
-                let region_cycles: HashMap<_, _> = report.cycle_tracker.into_iter().collect();
-
-                let metrics = WorkloadMetrics {
-                    name: format!("{}-{}", name, block_number),
-                    total_num_cycles,
-                    region_cycles,
-                };
-                reports.push(metrics);
-            }
-            reports
-        },
-    );
+    run_benchmark("zkm", |client_input: &ClientInput, network: &Genesis| {
+        let mut stdin = ZKMStdin::new();
+        stdin.write(client_input);
+        stdin.write(network);
+
+        let (mut public_values, report) = client.execute(STATELESS_ELF, stdin).run().unwrap();
+
+        BlockExecution {
+            total_num_cycles: report.total_instruction_count(),
+            region_cycles: report.cycle_tracker.into_iter().collect(),
+            cycles_unsupported: false,
+            output: public_values.read::<GuestOutput>(),
+            execution_report: None,
+        }
+    });
 }
@@ -0,0 +1,228 @@
+//! `xtask bench` – build a zkVM's guest, then run its host benchmark, with the
+//! zkVM's patch-set applied throughout
+//!
+//! The guest can also be built with part of the patch-set, to measure what the
+//! patches save: the results of such a variant go next to the regular ones, in
+//! `zkevm-metrics/<zkvm>-<variant>/`, for `zkevm-metrics savings` to compare.
+
+use anyhow::{Context, Result, bail};
+use std::{
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{
+    patch_set::{PatchSet, Subset},
+    workspace::PatchedWorkspace,
+};
+
+/// How the benchmark of a zkVM is built and run
+pub(crate) struct Zkvm {
+    /// Name, as given to `xtask bench`
+    pub(crate) name: &'static str,
+    /// Whether it builds with the patch-set `precompile-patches/<name>.toml`
+    patched: bool,
+    guest: Guest,
+    /// Host package
+    host: &'static str,
+    /// Directory of the host package, which it is run from
+    host_dir: &'static str,
+}
+
+/// How the guest ELF of a zkVM is built
+enum Guest {
+    /// By a command of the zkVM's toolchain, run from the guest's directory,
+    /// which writes the ELF to `elf` in the target directory for the host to
+    /// `include_bytes!`
+    Command {
+        dir: &'static str,
+        cargo_args: &'static [&'static str],
+        elf: &'static str,
+    },
+    /// By the host, either from its build script or at runtime
+    Host,
+}
+
+/// Every zkVM `xtask bench` can run, in the order `bench all` runs them
+pub(crate) const ZKVMS: &[Zkvm] = &[
+    Zkvm {
+        name: "succinct",
+        patched: true,
+        guest: Guest::Command {
+            dir: "crates/zkevm-succinct/program",
+            cargo_args: &["prove", "build"],
+            elf: "elf-compilation/riscv32im-succinct-zkvm-elf/release/succinct-guest",
+        },
+        host: "succinct-host",
+        host_dir: "crates/zkevm-succinct/host",
+    },
+    Zkvm {
+        name: "zkm",
+        patched: true,
+        guest: Guest::Host,
+        host: "zkm-host",
+        host_dir: "crates/zkevm-zkm/host",
+    },
+    Zkvm {
+        name: "risc0",
+        patched: true,
+        guest: Guest::Host,
+        host: "risc0-host",
+        host_dir: "crates/zkevm-risc0/host",
+    },
+    Zkvm {
+        name: "openvm",
+        patched: false,
+        guest: Guest::Host,
+        host: "openvm-host",
+        host_dir: "crates/zkevm-openvm/host",
+    },
+];
+
+/// The runs `xtask bench` makes of a zkVM
+pub(crate) enum Runs {
+    /// One, with the whole patch-set
+    Full,
+    /// One, with part of the patch-set
+    Subset(Subset),
+    /// One with the whole patch-set, one without each of its patches in turn,
+    /// and one without any
+    Savings,
+}
+
+/// The zkVMs `name` stands for: one of [`ZKVMS`], or all of them for `all`
+pub(crate) fn select(name: &str) -> Result<Vec<&'static Zkvm>> {
+    if name == "all" {
+        return Ok(ZKVMS.iter().collect());
+    }
+    match ZKVMS.iter().find(|zkvm| zkvm.name == name) {
+        Some(zkvm) => Ok(vec![zkvm]),
+        None => bail!(
+            "unknown zkVM `{name}` (available: {}, all)",
+            ZKVMS
+                .iter()
+                .map(|zkvm| zkvm.name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Make the `runs` of `zkvm`
+pub(crate) fn bench(
+    ws_root: &Path,
+    zkvm: &Zkvm,
+    runs: &Runs,
+    interrupted: &AtomicBool,
+) -> Result<()> {
+    let patch_set = if zkvm.patched {
+        PatchSet::load(&ws_root.join("precompile-patches"), zkvm.name)?
+    } else {
+        PatchSet::empty(zkvm.name)
+    };
+    let patch_sets = match runs {
+        Runs::Full => vec![patch_set],
+        Runs::Subset(subset) => vec![patch_set.subset(subset)?],
+        Runs::Savings => {
+            let mut patch_sets = Vec::new();
+            for (key, _) in &patch_set.patches {
+                patch_sets.push(patch_set.subset(&Subset::Without(vec![key.to_string()]))?);
+            }
+            if !patch_sets.is_empty() {
+                patch_sets.push(patch_set.subset(&Subset::Nothing)?);
+            }
+            patch_sets.insert(0, patch_set);
+            patch_sets
+        }
+    };
+
+    for patch_set in &patch_sets {
+        if let Some(variant) = &patch_set.variant {
+            println!("--- {} {variant}", zkvm.name);
+        }
+        run(ws_root, zkvm, patch_set, interrupted)?;
+    }
+    if patch_sets.len() > 1 {
+        println!(
+            "compare the runs with `cargo run -p zkevm-metrics -- savings zkevm-metrics --zkvm {}`",
+            zkvm.name
+        );
+    }
+    Ok(())
+}
+
+/// Apply `patch_set`, build the guest of `zkvm`, then run its host
+fn run(ws_root: &Path, zkvm: &Zkvm, patch_set: &PatchSet, interrupted: &AtomicBool) -> Result<()> {
+    let workspace = PatchedWorkspace::apply(ws_root, patch_set)?;
+
+    // 1 ── build the guest, unless the host does
+    if let Guest::Command {
+        dir,
+        cargo_args,
+        elf,
+    } = zkvm.guest
+    {
+        run_step(&workspace, ws_root, dir, cargo_args, interrupted)?;
+        let elf = workspace.target_dir().join(elf);
+        if !elf.is_file() {
+            bail!(
+                "`cargo {}` did not produce {}, where `{}` expects the guest ELF",
+                cargo_args.join(" "),
+                elf.display(),
+                zkvm.host
+            );
+        }
+    }
+
+    // 2 ── build and run the host
+    run_step(
+        &workspace,
+        ws_root,
+        zkvm.host_dir,
+        &["run", "--release", "-p", zkvm.host],
+        interrupted,
+    )
+}
+
+/// Run `cargo <cargo_args>` from `dir`, relative to the workspace root
+fn run_step(
+    workspace: &PatchedWorkspace,
+    ws_root: &Path,
+    dir: &str,
+    cargo_args: &[&str],
+    interrupted: &AtomicBool,
+) -> Result<()> {
+    let command = format!("cargo {}", cargo_args.join(" "));
+    println!("==> {command} (in {dir})");
+    let status = workspace
+        .cargo()
+        .args(cargo_args)
+        .current_dir(ws_root.join(dir))
+        .status()
+        .context("failed to invoke cargo")?;
+    if interrupted.load(Ordering::SeqCst) {
+        bail!("interrupted");
+    }
+    if !status.success() {
+        bail!("`{command}` failed ({status})");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zkvms_are_selected_by_name() {
+        let names =
+            |name| -> Vec<_> { select(name).unwrap().iter().map(|zkvm| zkvm.name).collect() };
+        assert_eq!(names("zkm"), ["zkm"]);
+        assert_eq!(names("all"), ["succinct", "zkm", "risc0", "openvm"]);
+        let err = select("jolt").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "unknown zkVM `jolt` (available: succinct, zkm, risc0, openvm, all)"
+        );
+    }
+}
@@ -0,0 +1,275 @@
+//! `xtask check` – verify the patch-sets against each other and against the
+//! dependency graph
+
+use anyhow::{Context, Result, bail};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+    process::Stdio,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use toml_edit::{DocumentMut, Item};
+
+use crate::{
+    patch_set::{PatchSet, package_name},
+    workspace::PatchedWorkspace,
+};
+
+/// A package of a resolved `Cargo.lock`
+struct LockedPackage {
+    name: String,
+    version: String,
+    /// `None` for path dependencies and workspace members
+    source: Option<String>,
+}
+
+/// Check every patch-set and return the problems found, if any
+///
+/// - a key must patch the same crate in every patch-set, and a crate must be
+///   patched under the same key, otherwise switching patch-sets can leave a
+///   stale patch behind
+/// - every patch must be used once Cargo resolves the workspace with its
+///   patch-set: the crate must be in the dependency graph, and the patched
+///   version must satisfy the version requirements on it
+pub(crate) fn check(
+    ws_root: &Path,
+    patch_sets: &[PatchSet],
+    interrupted: &AtomicBool,
+) -> Result<Vec<String>> {
+    let mut problems = check_consistency(patch_sets);
+    for patch_set in patch_sets {
+        println!("resolving the workspace with `{}`", patch_set.name);
+        problems.extend(check_resolution(ws_root, patch_set)?);
+        if interrupted.load(Ordering::SeqCst) {
+            bail!("interrupted");
+        }
+    }
+    Ok(problems)
+}
+
+/// Keys that patch different crates across patch-sets, and crates patched
+/// under different keys
+fn check_consistency(patch_sets: &[PatchSet]) -> Vec<String> {
+    // key -> package -> patch-sets, and package -> key -> patch-sets
+    let mut by_key: BTreeMap<&str, BTreeMap<&str, Vec<&str>>> = BTreeMap::new();
+    let mut by_package: BTreeMap<&str, BTreeMap<&str, Vec<&str>>> = BTreeMap::new();
+    for patch_set in patch_sets {
+        for (key, package) in patch_set.crates() {
+            let set = patch_set.name.as_str();
+            by_key
+                .entry(key)
+                .or_default()
+                .entry(package)
+                .or_default()
+                .push(set);
+            by_package
+                .entry(package)
+                .or_default()
+                .entry(key)
+                .or_default()
+                .push(set);
+        }
+    }
+
+    let claims = |claims: &BTreeMap<&str, Vec<&str>>| {
+        claims
+            .iter()
+            .map(|(claim, sets)| format!("`{claim}` in {}", sets.join(", ")))
+            .collect::<Vec<_>>()
+            .join("; ")
+    };
+    let mut problems = Vec::new();
+    for (key, packages) in by_key.iter().filter(|(_, packages)| packages.len() > 1) {
+        problems.push(format!(
+            "key `{key}` patches different crates: {}",
+            claims(packages)
+        ));
+    }
+    for (package, keys) in by_package.iter().filter(|(_, keys)| keys.len() > 1) {
+        problems.push(format!(
+            "crate `{package}` is patched under different keys: {}",
+            claims(keys)
+        ));
+    }
+    problems
+}
+
+/// Patches of `patch_set` that Cargo does not use when resolving the workspace
+fn check_resolution(ws_root: &Path, patch_set: &PatchSet) -> Result<Vec<String>> {
+    let workspace = PatchedWorkspace::apply(ws_root, patch_set)?;
+    let status = workspace
+        .cargo()
+        .args(["metadata", "--format-version", "1"])
+        .current_dir(ws_root)
+        .stdout(Stdio::null())
+        .status()
+        .context("failed to invoke cargo")?;
+    if !status.success() {
+        return Ok(vec![format!(
+            "{}: cargo could not resolve the workspace",
+            patch_set.name
+        )]);
+    }
+
+    let lock: DocumentMut = fs::read_to_string(workspace.lockfile())
+        .with_context(|| format!("reading {}", workspace.lockfile().display()))?
+        .parse()?;
+    let packages = locked_packages(lock.get("package"));
+    let unused = locked_packages(lock.get("patch").and_then(|patch| patch.get("unused")));
+
+    let mut problems = Vec::new();
+    for (key, item) in &patch_set.patches {
+        let package = package_name(key, item);
+        let source = item
+            .get("git")
+            .and_then(Item::as_str)
+            .map(|git| format!("git+{git}"));
+        let patched = |p: &LockedPackage| {
+            p.name == package
+                && match (&source, &p.source) {
+                    (Some(source), Some(locked)) => locked.starts_with(source.as_str()),
+                    (None, locked) => locked.is_none(),
+                    (Some(_), None) => false,
+                }
+        };
+        if packages.iter().any(patched) {
+            continue;
+        }
+
+        let patched_version = unused
+            .iter()
+            .find(|p| patched(p))
+            .map(|p| format!(" (patched version {})", p.version))
+            .unwrap_or_default();
+        let required: BTreeSet<&str> = packages
+            .iter()
+            .filter(|p| p.name == package)
+            .map(|p| p.version.as_str())
+            .collect();
+        problems.push(if required.is_empty() {
+            format!(
+                "{}: patch `{key}` is unused, `{package}` is not in the dependency graph",
+                patch_set.name
+            )
+        } else {
+            format!(
+                "{}: patch `{key}` is unused, the dependency graph uses `{package}` {} \
+                 which the patch does not match{patched_version}",
+                patch_set.name,
+                required.into_iter().collect::<Vec<_>>().join(", ")
+            )
+        });
+    }
+    Ok(problems)
+}
+
+/// The `[[package]]` (or `[[patch.unused]]`) entries of a lockfile
+fn locked_packages(item: Option<&Item>) -> Vec<LockedPackage> {
+    let Some(tables) = item.and_then(Item::as_array_of_tables) else {
+        return Vec::new();
+    };
+    tables
+        .iter()
+        .filter_map(|table| {
+            let field = |name: &str| table.get(name).and_then(Item::as_str).map(str::to_string);
+            Some(LockedPackage {
+                name: field("name")?,
+                version: field("version")?,
+                source: field("source"),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patch_set(name: &str, patches: &str) -> PatchSet {
+        let mut patch_set = PatchSet::empty(name);
+        patch_set.patches = patches.parse::<DocumentMut>().unwrap().as_table().clone();
+        patch_set
+    }
+
+    #[test]
+    fn keys_patching_the_same_crate_are_consistent() {
+        let patch_sets = [
+            patch_set("a", r#"sha2 = { git = "https://a/sha2" }"#),
+            patch_set(
+                "b",
+                r#"sha2 = { git = "https://b/sha2", package = "sha2" }"#,
+            ),
+        ];
+        assert!(check_consistency(&patch_sets).is_empty());
+    }
+
+    #[test]
+    fn keys_and_crates_must_match_across_patch_sets() {
+        let patch_sets = [
+            patch_set("a", r#"sha2 = { git = "https://a/sha2" }"#),
+            patch_set(
+                "b",
+                r#"sha2 = { git = "https://b/sha3", package = "sha3" }"#,
+            ),
+            patch_set(
+                "c",
+                r#"sha2-v0 = { git = "https://c/sha2", package = "sha2" }"#,
+            ),
+        ];
+        assert_eq!(
+            check_consistency(&patch_sets),
+            [
+                "key `sha2` patches different crates: `sha2` in a; `sha3` in b",
+                "crate `sha2` is patched under different keys: `sha2` in a; `sha2-v0` in c",
+            ]
+        );
+    }
+
+    #[test]
+    fn lockfile_packages_are_read() {
+        let lock: DocumentMut = r#"
+            version = 4
+
+            [[package]]
+            name = "sha2"
+            version = "0.10.8"
+            source = "git+https://github.com/sp1-patches/RustCrypto-hashes#abc"
+
+            [[package]]
+            name = "xtask"
+            version = "0.1.0"
+
+            [[package]]
+            name = "incomplete"
+
+            [[patch.unused]]
+            name = "k256"
+            version = "0.13.4"
+        "#
+        .parse()
+        .unwrap();
+
+        let packages = locked_packages(lock.get("package"));
+        let packages: Vec<_> = packages
+            .iter()
+            .map(|p| (p.name.as_str(), p.version.as_str(), p.source.as_deref()))
+            .collect();
+        assert_eq!(
+            packages,
+            [
+                (
+                    "sha2",
+                    "0.10.8",
+                    Some("git+https://github.com/sp1-patches/RustCrypto-hashes#abc")
+                ),
+                ("xtask", "0.1.0", None),
+            ]
+        );
+
+        let unused = locked_packages(lock.get("patch").and_then(|patch| patch.get("unused")));
+        assert_eq!(unused.len(), 1);
+        assert_eq!(unused[0].name, "k256");
+        assert!(locked_packages(lock.get("metadata")).is_empty());
+    }
+}
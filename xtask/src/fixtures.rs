@@ -0,0 +1,462 @@
+//! `xtask fixtures` – install the execution-spec-tests fixtures the witness
+//! generator reads
+//!
+//! Each release is installed side by side in `zkevm-fixtures/versions/<tag>/`,
+//! and `zkevm-fixtures/fixtures`, where the witness generator looks for them,
+//! links to the active one. `zkevm-fixtures/manifest.toml` records what is
+//! installed and which release is active.
+//!
+//! The SHA-256 of every archive is verified before anything is extracted,
+//! against the value pinned for its tag in `fixtures.toml` or given on the
+//! command-line. Installing from a local archive never touches the network.
+
+use anyhow::{Context, Result, bail};
+use flate2::read::GzDecoder;
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use toml_edit::{DocumentMut, Item, Table, value};
+
+#[cfg(unix)]
+use std::os::unix::fs::symlink as symlink_dir;
+#[cfg(windows)]
+use std::os::windows::fs::symlink_dir;
+
+/// Repository whose releases hold the fixtures
+const REPO: &str = "ethereum/execution-spec-tests";
+/// Release asset holding the fixtures
+const ASSET_NAME: &str = "fixtures_zkevm.tar.gz";
+/// Pinned releases, relative to the workspace root
+const PINS_FILE: &str = "fixtures.toml";
+/// Where fixtures are installed, relative to the workspace root
+const FIXTURES_DIR: &str = "zkevm-fixtures";
+/// Installed releases, in the fixtures directory
+const MANIFEST_FILE: &str = "manifest.toml";
+
+/// Where the archive of a release comes from
+pub(crate) enum Source {
+    /// The release asset on GitHub, or another URL
+    Download(Option<String>),
+    /// An archive on disk
+    Archive(PathBuf),
+}
+
+/// Install the fixtures of release `tag` (the pinned default if `None`) from
+/// `source`, and make them the active ones
+pub(crate) fn install(
+    ws_root: &Path,
+    tag: Option<String>,
+    source: Source,
+    sha256: Option<String>,
+) -> Result<()> {
+    let pins = read_toml(&ws_root.join(PINS_FILE))?;
+    let tag = match tag {
+        Some(tag) => tag,
+        None => pins
+            .get("default")
+            .and_then(Item::as_str)
+            .map(str::to_string)
+            .with_context(|| format!("no tag given, and {PINS_FILE} pins no default"))?,
+    };
+    let pinned = pins
+        .get("releases")
+        .and_then(|releases| releases.get(&tag))
+        .and_then(|release| release.get("sha256"))
+        .and_then(Item::as_str);
+    let expected = match (sha256, pinned) {
+        (Some(given), Some(pinned)) if !given.eq_ignore_ascii_case(pinned) => bail!(
+            "the SHA-256 given for `{tag}` differs from the one pinned in {PINS_FILE} ({pinned})"
+        ),
+        (Some(given), _) => given.to_ascii_lowercase(),
+        (None, Some(pinned)) => pinned.to_ascii_lowercase(),
+        (None, None) => String::new(),
+    };
+
+    let fixtures_dir = ws_root.join(FIXTURES_DIR);
+    let mut manifest = read_toml(&fixtures_dir.join(MANIFEST_FILE))?;
+    let installed = |manifest: &DocumentMut| {
+        manifest
+            .get("installed")
+            .and_then(|installed| installed.get(&tag))
+            .and_then(|release| release.get("sha256"))
+            .and_then(Item::as_str)
+            .map(str::to_string)
+    };
+    if installed(&manifest).is_some_and(|sha256| expected.is_empty() || sha256 == expected) {
+        println!("`{tag}` is already installed");
+        return activate(&fixtures_dir, &mut manifest, &tag);
+    }
+
+    // 1 ── fetch the archive and verify it
+    let (archive, source, downloaded) = match source {
+        Source::Archive(path) => {
+            let source = path.display().to_string();
+            (path, source, false)
+        }
+        Source::Download(url) => {
+            let url = url.unwrap_or_else(|| release_url(&tag));
+            let path = fixtures_dir.join(format!("{}.tar.gz.part", dir_name(&tag)));
+            // An archive kept by an attempt that had no SHA-256 to verify it against
+            if path.is_file() && !expected.is_empty() && sha256_file(&path)? == expected {
+                println!("reusing {}", path.display());
+            } else {
+                download(&url, &path)?;
+            }
+            (path, url, true)
+        }
+    };
+    let actual = sha256_file(&archive)?;
+    if actual != expected {
+        if expected.is_empty() {
+            // Kept, so that installing it once its SHA-256 is pinned does not
+            // download it again
+            bail!(
+                "no SHA-256 pinned for `{tag}` in {PINS_FILE}; the archive's is {actual}, \
+                 pin it or pass it with --sha256 once you trust it"
+            );
+        }
+        if downloaded {
+            fs::remove_file(&archive)?;
+        }
+        bail!("SHA-256 mismatch for `{tag}`: expected {expected}, got {actual}");
+    }
+
+    // 2 ── extract it next to the other releases
+    let dir = Path::new("versions").join(dir_name(&tag));
+    let dest = fixtures_dir.join(&dir);
+    let partial = fixtures_dir
+        .join("versions")
+        .join(format!("{}.part", dir_name(&tag)));
+    for stale in [&dest, &partial] {
+        if stale.exists() {
+            fs::remove_dir_all(stale)?;
+        }
+    }
+    println!("extracting {ASSET_NAME} to {}", dest.display());
+    tar::Archive::new(GzDecoder::new(File::open(&archive)?))
+        .unpack(&partial)
+        .with_context(|| format!("extracting {}", archive.display()))?;
+    if !partial.join("fixtures").is_dir() {
+        fs::remove_dir_all(&partial)?;
+        bail!("{} holds no `fixtures` directory", archive.display());
+    }
+    fs::rename(&partial, &dest)?;
+    if downloaded {
+        fs::remove_file(&archive)?;
+    }
+
+    // 3 ── record it
+    let installed_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let mut release = Table::new();
+    release["dir"] = value(dir.to_string_lossy().replace('\\', "/"));
+    release["sha256"] = value(actual);
+    release["source"] = value(source);
+    release["installed_at"] = value(i64::try_from(installed_at)?);
+    let mut installed = Table::new();
+    installed.set_implicit(true);
+    manifest["installed"].or_insert(Item::Table(installed))[&tag] = Item::Table(release);
+    activate(&fixtures_dir, &mut manifest, &tag)
+}
+
+/// Make the installed release `tag` the active one
+pub(crate) fn use_release(ws_root: &Path, tag: &str) -> Result<()> {
+    let fixtures_dir = ws_root.join(FIXTURES_DIR);
+    let mut manifest = read_toml(&fixtures_dir.join(MANIFEST_FILE))?;
+    activate(&fixtures_dir, &mut manifest, tag)
+}
+
+/// Print the installed releases, marking the active one
+pub(crate) fn list(ws_root: &Path) -> Result<()> {
+    let manifest = read_toml(&ws_root.join(FIXTURES_DIR).join(MANIFEST_FILE))?;
+    let active = manifest.get("active").and_then(Item::as_str);
+    let Some(installed) = manifest.get("installed").and_then(Item::as_table) else {
+        println!("no fixtures installed, see `xtask fixtures install`");
+        return Ok(());
+    };
+    for (tag, release) in installed {
+        let field = |name: &str| release.get(name).and_then(Item::as_str).unwrap_or("?");
+        println!(
+            "{} {tag}  sha256 {}  from {}",
+            if active == Some(tag) { "*" } else { " " },
+            field("sha256"),
+            field("source")
+        );
+    }
+    Ok(())
+}
+
+/// Point `zkevm-fixtures/fixtures` at the installed release `tag`, and record
+/// it as active
+fn activate(fixtures_dir: &Path, manifest: &mut DocumentMut, tag: &str) -> Result<()> {
+    let dir = manifest
+        .get("installed")
+        .and_then(|installed| installed.get(tag))
+        .and_then(|release| release.get("dir"))
+        .and_then(Item::as_str)
+        .with_context(|| format!("`{tag}` is not installed"))?;
+    let link = fixtures_dir.join("fixtures");
+    match fs::symlink_metadata(&link) {
+        Ok(metadata) if metadata.file_type().is_symlink() => {
+            fs::remove_file(&link).or_else(|_| fs::remove_dir(&link))?;
+        }
+        Ok(_) => bail!(
+            "{} is not managed by xtask (extracted by the download script?), \
+             remove it to install fixtures side by side",
+            link.display()
+        ),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err.into()),
+    }
+    symlink_dir(Path::new(dir).join("fixtures"), &link)
+        .with_context(|| format!("linking {}", link.display()))?;
+
+    manifest["active"] = value(tag);
+    fs::write(fixtures_dir.join(MANIFEST_FILE), manifest.to_string())?;
+    println!("fixtures `{tag}` are active");
+    Ok(())
+}
+
+/// Download `url` to `path`
+fn download(url: &str, path: &Path) -> Result<()> {
+    println!("downloading {url}");
+    let response = ureq::get(url)
+        .call()
+        .with_context(|| format!("downloading {url}"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = File::create(path).with_context(|| format!("creating {}", path.display()))?;
+    io::copy(&mut response.into_reader(), &mut file)
+        .with_context(|| format!("downloading {url}"))?;
+    Ok(())
+}
+
+/// URL of the fixtures asset of release `tag`
+fn release_url(tag: &str) -> String {
+    format!(
+        "https://github.com/{REPO}/releases/download/{}/{ASSET_NAME}",
+        tag.replace('@', "%40")
+    )
+}
+
+/// Lowercase hex SHA-256 of the file at `path`
+fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+/// Directory a release is installed to, e.g. `zkevm-v0.0.1` for `zkevm@v0.0.1`
+fn dir_name(tag: &str) -> String {
+    tag.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "._-".contains(c) {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Parse the TOML file at `path`, empty if it does not exist
+fn read_toml(path: &Path) -> Result<DocumentMut> {
+    match fs::read_to_string(path) {
+        Ok(contents) => contents
+            .parse()
+            .with_context(|| format!("parsing {}", path.display())),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(DocumentMut::new()),
+        Err(err) => Err(err).with_context(|| format!("reading {}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{Compression, write::GzEncoder};
+
+    /// A fixtures archive holding a single blockchain test named `name`
+    fn archive(dir: &Path, name: &str) -> PathBuf {
+        let path = dir.join(format!("{name}.tar.gz"));
+        let mut builder = tar::Builder::new(GzEncoder::new(
+            File::create(&path).unwrap(),
+            Compression::fast(),
+        ));
+        let contents = b"{}";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(
+                &mut header,
+                format!("fixtures/blockchain_tests/{name}.json"),
+                &contents[..],
+            )
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+        path
+    }
+
+    fn active(ws_root: &Path) -> Option<String> {
+        let manifest = read_toml(&ws_root.join(FIXTURES_DIR).join(MANIFEST_FILE)).unwrap();
+        manifest.get("active")?.as_str().map(str::to_string)
+    }
+
+    #[test]
+    fn tags_name_directories_and_urls() {
+        assert_eq!(dir_name("zkevm@v0.0.1"), "zkevm-v0.0.1");
+        assert_eq!(dir_name("a/b c"), "a-b-c");
+        assert_eq!(
+            release_url("zkevm@v0.0.1"),
+            "https://github.com/ethereum/execution-spec-tests/releases/download/zkevm%40v0.0.1/fixtures_zkevm.tar.gz"
+        );
+    }
+
+    #[test]
+    fn files_are_hashed_as_lowercase_hex() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("abc");
+        fs::write(&path, "abc").unwrap();
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn releases_are_installed_side_by_side() {
+        let ws = tempfile::tempdir().unwrap();
+        let ws_root = ws.path();
+        let first = archive(ws_root, "first");
+        let second = archive(ws_root, "second");
+        let first_sha256 = sha256_file(&first).unwrap();
+        fs::write(
+            ws_root.join(PINS_FILE),
+            format!("default = \"t@1\"\n\n[releases.\"t@1\"]\nsha256 = \"{first_sha256}\"\n"),
+        )
+        .unwrap();
+
+        // The pinned default
+        install(ws_root, None, Source::Archive(first.clone()), None).unwrap();
+        assert_eq!(active(ws_root).as_deref(), Some("t@1"));
+        let fixtures = ws_root.join(FIXTURES_DIR).join("fixtures");
+        assert!(fixtures.join("blockchain_tests/first.json").is_file());
+
+        // A release that is not pinned needs its SHA-256
+        let err = install(
+            ws_root,
+            Some("t@2".into()),
+            Source::Archive(second.clone()),
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("no SHA-256 pinned"), "{err}");
+        let err = install(
+            ws_root,
+            Some("t@2".into()),
+            Source::Archive(second.clone()),
+            Some("00".into()),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("SHA-256 mismatch"), "{err}");
+        // and cannot contradict the pinned one
+        let err = install(
+            ws_root,
+            Some("t@1".into()),
+            Source::Archive(first),
+            Some("00".into()),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("differs"), "{err}");
+
+        let second_sha256 = sha256_file(&second).unwrap();
+        install(
+            ws_root,
+            Some("t@2".into()),
+            Source::Archive(second),
+            Some(second_sha256.to_uppercase()),
+        )
+        .unwrap();
+        assert_eq!(active(ws_root).as_deref(), Some("t@2"));
+        assert!(fixtures.join("blockchain_tests/second.json").is_file());
+        assert!(
+            ws_root
+                .join(FIXTURES_DIR)
+                .join("versions/t-1/fixtures/blockchain_tests/first.json")
+                .is_file()
+        );
+
+        // Switching back does not reinstall anything
+        use_release(ws_root, "t@1").unwrap();
+        assert_eq!(active(ws_root).as_deref(), Some("t@1"));
+        assert!(fixtures.join("blockchain_tests/first.json").is_file());
+        let manifest = read_toml(&ws_root.join(FIXTURES_DIR).join(MANIFEST_FILE)).unwrap();
+        assert_eq!(
+            manifest["installed"]["t@2"]["sha256"].as_str(),
+            Some(second_sha256.as_str())
+        );
+        assert_eq!(
+            manifest["installed"]["t@1"]["dir"].as_str(),
+            Some("versions/t-1")
+        );
+
+        let err = use_release(ws_root, "t@3").unwrap_err();
+        assert!(err.to_string().contains("not installed"), "{err}");
+    }
+
+    #[test]
+    fn kept_downloads_are_reused_once_pinned() {
+        let ws = tempfile::tempdir().unwrap();
+        let ws_root = ws.path();
+        let archive = archive(ws_root, "first");
+        let sha256 = sha256_file(&archive).unwrap();
+        // Left by an attempt without a pinned SHA-256
+        let part = ws_root.join(FIXTURES_DIR).join("t-1.tar.gz.part");
+        fs::create_dir_all(part.parent().unwrap()).unwrap();
+        fs::copy(&archive, &part).unwrap();
+
+        // Nothing listens there, so this only succeeds without downloading
+        let url = Some("http://127.0.0.1:9/fixtures_zkevm.tar.gz".to_string());
+        install(
+            ws_root,
+            Some("t@1".into()),
+            Source::Download(url),
+            Some(sha256),
+        )
+        .unwrap();
+        assert_eq!(active(ws_root).as_deref(), Some("t@1"));
+        assert!(!part.exists());
+    }
+
+    #[test]
+    fn unmanaged_fixtures_are_left_alone() {
+        let ws = tempfile::tempdir().unwrap();
+        let ws_root = ws.path();
+        let archive = archive(ws_root, "first");
+        let sha256 = sha256_file(&archive).unwrap();
+        // Extracted by the download script
+        fs::create_dir_all(ws_root.join(FIXTURES_DIR).join("fixtures")).unwrap();
+
+        let err = install(
+            ws_root,
+            Some("t@1".into()),
+            Source::Archive(archive),
+            Some(sha256),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("not managed by xtask"), "{err}");
+    }
+}
@@ -4,90 +4,306 @@
 //! Run directly: ./target/release/xtask succinct -- build -p succinct
 //!
 //! (or add aliases in .cargo/config.toml, see README)
+//!
+//! Inspect the patch-sets:
+//!
+//!   xtask list            patch-sets and the crates each one patches
+//!   xtask show succinct   the [patch.crates-io] table `succinct` applies,
+//!                         with the file each entry comes from
+//!   xtask check           every patch is used, and keys are claimed consistently
+//!
+//! Run a benchmark, building the guest and running the host with the zkVM's
+//! patch-set:
+//!
+//!   xtask bench succinct  one zkVM
+//!   xtask bench all       every zkVM in turn
+//!   xtask bench succinct --without sha2,k256
+//!                         with part of the patch-set, see also --only,
+//!                         --no-patches and --savings
+//!
+//! Install the fixtures the witness generator reads:
+//!
+//!   xtask fixtures install [TAG] [--archive <FILE>]
+
+mod bench;
+mod check;
+mod fixtures;
+mod patch_set;
+mod workspace;
 
 use anyhow::{Context, Result, bail};
-use clap::Parser;
-use std::{collections::HashSet, fs, path::PathBuf, process::Command};
-use toml_edit::{DocumentMut, Item, Table};
+use clap::{Args, Parser, Subcommand};
+use std::{
+    path::{Path, PathBuf},
+    process::{ExitCode, ExitStatus},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use patch_set::{PatchSet, Subset};
+use workspace::PatchedWorkspace;
 
 /// Inject one of the `precompile-patches/*.toml` files into the workspace
 /// `Cargo.toml`, replacing previous precompile patches, then forward the rest
-/// of the command-line to `cargo`.
+/// of the command-line to `cargo`. The manifest is restored once `cargo` exits.
 #[derive(Parser)]
 #[command(
     author,
     version,
     about = "Patch-set injector for the workspace",
-    trailing_var_arg = true,
+    override_usage = "xtask <PATCH-SET> [-- <CARGO ARGS>...]\n       xtask <COMMAND>",
     disable_help_subcommand = true
 )]
 struct Cli {
-    /// Patch-set name (file stem of precompile-patches/<name>.toml)
-    patch: String,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the patch-sets and the crates each one patches
+    List,
+    /// Print the [patch.crates-io] table a patch-set applies
+    Show {
+        /// Patch-set name (file stem of precompile-patches/<name>.toml)
+        name: String,
+    },
+    /// Check that every patch is used and that patch-sets claim keys consistently
+    Check,
+    /// Build a zkVM's guest and run its host benchmark, with its patch-set applied
+    Bench(BenchArgs),
+    /// Install and switch between releases of the test fixtures
+    #[command(subcommand)]
+    Fixtures(FixturesCommand),
+    /// `<patch-set> [-- <cargo args>...]`: apply the patch-set, then run Cargo
+    #[command(external_subcommand)]
+    Cargo(Vec<String>),
+}
+
+#[derive(Args)]
+struct BenchArgs {
+    /// zkVM to benchmark, or `all` to benchmark every zkVM in turn
+    zkvm: String,
+    /// Build without these patches, by [patch.crates-io] key
+    #[arg(long, value_delimiter = ',', conflicts_with_all = ["only", "no_patches", "savings"])]
+    without: Vec<String>,
+    /// Build with only these patches, by [patch.crates-io] key
+    #[arg(long, value_delimiter = ',', conflicts_with_all = ["no_patches", "savings"])]
+    only: Vec<String>,
+    /// Build without any patch
+    #[arg(long, conflicts_with = "savings")]
+    no_patches: bool,
+    /// Run with the whole patch-set, without each patch in turn and without any
+    /// patch, to measure the cycles each patch saves
+    #[arg(long)]
+    savings: bool,
+}
 
-    /// Everything after <patch> is passed straight to Cargo
-    cargo_args: Vec<String>,
+#[derive(Subcommand)]
+enum FixturesCommand {
+    /// Install a release of the fixtures and make it the active one
+    Install(InstallArgs),
+    /// Make an installed release the active one
+    Use {
+        /// Release tag, e.g. `zkevm@v0.0.1`
+        tag: String,
+    },
+    /// List the installed releases
+    List,
 }
 
-fn main() -> Result<()> {
+#[derive(Args)]
+struct InstallArgs {
+    /// Release tag, e.g. `zkevm@v0.0.1` (default: the one pinned in fixtures.toml)
+    tag: Option<String>,
+    /// Install from this archive instead of downloading it
+    #[arg(long, conflicts_with = "url")]
+    archive: Option<PathBuf>,
+    /// Download the archive from this URL instead of the GitHub release
+    #[arg(long)]
+    url: Option<String>,
+    /// Expected SHA-256 of the archive, for tags not pinned in fixtures.toml
+    #[arg(long)]
+    sha256: Option<String>,
+}
+
+fn main() -> Result<ExitCode> {
     let cli = Cli::parse();
 
     // workspace-root/Cargo.toml  (xtask is at <root>/xtask)
     let ws_root = workspace_root()?;
-    let manifest_path = ws_root.join("Cargo.toml");
-
-    // 1 ── read root manifest
-    let manifest_src = fs::read_to_string(&manifest_path)
-        .with_context(|| format!("reading {}", manifest_path.display()))?;
-    let mut root_doc: DocumentMut = manifest_src.parse()?;
-
-    // 2 ── remove only the keys we “own”, ie the keys in the precompile-patches
     let precompile_dir = ws_root.join("precompile-patches");
-    let owned_keys = gather_owned_keys(&precompile_dir)?;
-    {
-        let ci = root_doc["patch"]["crates-io"]
-            .or_insert(Item::Table(Table::new()))
-            .as_table_mut()
-            .unwrap();
-        for key in &owned_keys {
-            ci.remove(key);
+
+    match cli.command {
+        Command::List => {
+            for patch_set in PatchSet::load_all(&precompile_dir)? {
+                let crates: Vec<String> = patch_set
+                    .crates()
+                    .map(|(key, package)| {
+                        if key == package {
+                            key.to_string()
+                        } else {
+                            format!("{key} ({package})")
+                        }
+                    })
+                    .collect();
+                if patch_set.extends.is_empty() {
+                    println!("{}: {}", patch_set.name, crates.join(", "));
+                } else {
+                    println!(
+                        "{} (extends {}): {}",
+                        patch_set.name,
+                        patch_set.extends.join(", "),
+                        crates.join(", ")
+                    );
+                }
+            }
         }
-    }
+        Command::Show { name } => {
+            let patch_set = PatchSet::load(&precompile_dir, &name)?;
+            println!("# {}", patch_set.path.display());
+            if !patch_set.extends.is_empty() {
+                println!("# extends {}", patch_set.extends.join(", "));
+            }
+            for (key, removed_by) in &patch_set.removed {
+                println!("# `{key}` removed by {removed_by}");
+            }
+            println!("[patch.crates-io]");
+            for (key, item) in &patch_set.patches {
+                let provenance = &patch_set.provenance[key];
+                let mut origin = format!("from {}", provenance.defined_in);
+                if !provenance.overrides.is_empty() {
+                    origin += &format!(", overrides {}", provenance.overrides.join(", "));
+                }
+                println!("{key} = {}  # {origin}", item.to_string().trim());
+            }
+        }
+        Command::Check => {
+            let patch_sets = PatchSet::load_all(&precompile_dir)?;
+            let interrupted = catch_interrupts()?;
+            let problems = check::check(&ws_root, &patch_sets, &interrupted)?;
+            if !problems.is_empty() {
+                for problem in &problems {
+                    eprintln!("error: {problem}");
+                }
+                return Ok(ExitCode::FAILURE);
+            }
+            println!(
+                "all {} patch-sets are consistent and fully used",
+                patch_sets.len()
+            );
+        }
+        Command::Bench(args) => {
+            let zkvms = bench::select(&args.zkvm)?;
+            let interrupted = catch_interrupts()?;
+            let runs = if args.savings {
+                bench::Runs::Savings
+            } else if args.no_patches {
+                bench::Runs::Subset(Subset::Nothing)
+            } else if !args.without.is_empty() {
+                bench::Runs::Subset(Subset::Without(args.without))
+            } else if !args.only.is_empty() {
+                bench::Runs::Subset(Subset::Only(args.only))
+            } else {
+                bench::Runs::Full
+            };
+            if let [zkvm] = zkvms.as_slice() {
+                bench::bench(&ws_root, zkvm, &runs, &interrupted)?;
+                return Ok(ExitCode::SUCCESS);
+            }
+            if matches!(
+                runs,
+                bench::Runs::Subset(Subset::Without(_) | Subset::Only(_))
+            ) {
+                bail!("--without and --only name the patches of a single zkVM");
+            }
 
-    // 3 ── insert the selected patch-set
-    let chosen_file = precompile_dir.join(format!("{}.toml", cli.patch));
-    if !chosen_file.exists() {
-        bail!(
-            "unknown patch-set `{}` (available: {})",
-            cli.patch,
-            display_patch_sets(&precompile_dir)?
-        );
-    }
-    let chosen_src = fs::read_to_string(&chosen_file)?;
-    let chosen_doc: DocumentMut = chosen_src.parse()?;
-    if let Some(tbl) = chosen_doc
-        .get("patch")
-        .and_then(|p| p.get("crates-io"))
-        .and_then(Item::as_table)
-    {
-        let ci = root_doc["patch"]["crates-io"].as_table_mut().unwrap();
-        for (k, v) in tbl {
-            ci[k] = v.clone(); // overwrite / insert
+            // `all`: a failing zkVM does not prevent benchmarking the others
+            let mut failed = Vec::new();
+            for zkvm in zkvms {
+                println!("=== {}", zkvm.name);
+                if let Err(err) = bench::bench(&ws_root, zkvm, &runs, &interrupted) {
+                    eprintln!("error: {}: {err:#}", zkvm.name);
+                    failed.push(zkvm.name);
+                }
+                if interrupted.load(Ordering::SeqCst) {
+                    return Ok(ExitCode::from(130));
+                }
+            }
+            if !failed.is_empty() {
+                eprintln!("benchmarks failed for: {}", failed.join(", "));
+                return Ok(ExitCode::FAILURE);
+            }
+            println!("all {} benchmarks ran", bench::ZKVMS.len());
+        }
+        Command::Fixtures(FixturesCommand::Install(args)) => {
+            let source = match args.archive {
+                Some(archive) => fixtures::Source::Archive(archive),
+                None => fixtures::Source::Download(args.url),
+            };
+            fixtures::install(&ws_root, args.tag, source, args.sha256)?;
+        }
+        Command::Fixtures(FixturesCommand::Use { tag }) => fixtures::use_release(&ws_root, &tag)?,
+        Command::Fixtures(FixturesCommand::List) => fixtures::list(&ws_root)?,
+        Command::Cargo(args) => {
+            let Some((patch, cargo_args)) = args.split_first() else {
+                bail!("missing patch-set name");
+            };
+            // Everything after `--` is passed straight to Cargo
+            let cargo_args = match cargo_args.first().map(String::as_str) {
+                Some("--") => &cargo_args[1..],
+                _ => cargo_args,
+            };
+            let patch_set = PatchSet::load(&precompile_dir, patch)?;
+            let interrupted = catch_interrupts()?;
+            return run_cargo(&ws_root, &patch_set, cargo_args, &interrupted);
         }
-    } else {
-        bail!("{} has no [patch.crates-io] table", chosen_file.display());
     }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Record Ctrl-C in the returned flag instead of exiting
+///
+/// Ctrl-C reaches Cargo as well, as it runs in the same process group. Let
+/// Cargo wind down, then restore the manifest and lockfile instead of dying
+/// with it. Only the commands that patch the workspace catch it; the others
+/// stop right away.
+fn catch_interrupts() -> Result<Arc<AtomicBool>> {
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&interrupted);
+    ctrlc::set_handler(move || flag.store(true, Ordering::SeqCst))
+        .context("installing the Ctrl-C handler")?;
+    Ok(interrupted)
+}
 
-    // 4 ── write the updated manifest back
-    fs::write(&manifest_path, root_doc.to_string())
-        .with_context(|| format!("writing {}", manifest_path.display()))?;
+/// Apply `patch_set`, run `cargo <cargo_args>`, then restore the workspace
+fn run_cargo(
+    ws_root: &Path,
+    patch_set: &PatchSet,
+    cargo_args: &[String],
+    interrupted: &AtomicBool,
+) -> Result<ExitCode> {
+    let workspace = PatchedWorkspace::apply(ws_root, patch_set)?;
+    if interrupted.load(Ordering::SeqCst) {
+        return Ok(ExitCode::from(130));
+    }
 
-    // 5 ── forward to Cargo
-    let status = Command::new("cargo")
-        .args(&cli.cargo_args)
+    let status = workspace
+        .cargo()
+        .args(cargo_args)
         .status()
         .context("failed to invoke cargo")?;
-    std::process::exit(status.code().unwrap_or(1));
+    Ok(exit_code(status))
+}
+
+/// Exit with the same code as a child process
+fn exit_code(status: ExitStatus) -> ExitCode {
+    status
+        .code()
+        .and_then(|code| u8::try_from(code).ok())
+        .map_or(ExitCode::FAILURE, ExitCode::from)
 }
 
 /// repo root (assumes xtask lives in <root>/xtask)
@@ -96,41 +312,3 @@ fn workspace_root() -> Result<PathBuf> {
     p.pop();
     Ok(p)
 }
-
-/// All crate-keys that occur in any precompile-patches/*.toml
-fn gather_owned_keys(dir: &PathBuf) -> Result<HashSet<String>> {
-    let mut keys = HashSet::new();
-    for entry in fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))? {
-        let path = entry?.path();
-        if path.extension().and_then(|s| s.to_str()) != Some("toml") {
-            continue;
-        }
-        let src = fs::read_to_string(&path)?;
-        let doc: DocumentMut = src.parse()?;
-        if let Some(tbl) = doc
-            .get("patch")
-            .and_then(|p| p.get("crates-io"))
-            .and_then(Item::as_table)
-        {
-            for (k, _) in tbl {
-                keys.insert(k.to_string());
-            }
-        }
-    }
-    Ok(keys)
-}
-
-/// Pretty-print available patch-set names
-fn display_patch_sets(dir: &PathBuf) -> Result<String> {
-    let mut names = Vec::new();
-    for entry in fs::read_dir(dir)? {
-        let path = entry?.path();
-        if path.extension().and_then(|s| s.to_str()) == Some("toml") {
-            if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
-                names.push(stem.to_string());
-            }
-        }
-    }
-    names.sort();
-    Ok(names.join(", "))
-}
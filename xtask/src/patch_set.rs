@@ -0,0 +1,468 @@
+//! Patch-sets: the `[patch.crates-io]` tables of `precompile-patches/*.toml`
+//!
+//! A patch-set can build on others instead of repeating their entries:
+//!
+//! ```toml
+//! extends = "common"          # or a list, merged in order
+//! remove = ["sha3"]           # drop inherited entries
+//!
+//! [patch.crates-io]           # add entries, or override inherited ones
+//! sha2 = { git = "...", tag = "..." }
+//! ```
+//!
+//! Resolving a patch-set yields its effective table, and where each entry of
+//! it came from.
+
+use anyhow::{Context, Result, bail};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use toml_edit::{DocumentMut, Item, Table};
+
+/// One `precompile-patches/<name>.toml` file, resolved
+pub(crate) struct PatchSet {
+    /// File stem, e.g. `succinct`
+    pub(crate) name: String,
+    pub(crate) path: PathBuf,
+    /// The patch-sets it extends, in merge order
+    pub(crate) extends: Vec<String>,
+    /// Effective `[patch.crates-io]` table, inherited entries included
+    pub(crate) patches: Table,
+    /// Where each entry of `patches` came from, by key
+    pub(crate) provenance: BTreeMap<String, Provenance>,
+    /// Inherited keys that were removed, with the patch-set that removed them
+    pub(crate) removed: BTreeMap<String, String>,
+    /// Variant of the build it stands for when only part of the file's entries
+    /// are kept, e.g. `without-sha2`
+    pub(crate) variant: Option<String>,
+}
+
+/// Where an entry of a resolved patch-set came from
+#[derive(Clone)]
+pub(crate) struct Provenance {
+    /// Patch-set whose file defines the entry
+    pub(crate) defined_in: String,
+    /// Patch-sets whose definitions of the key it overrides, latest first
+    pub(crate) overrides: Vec<String>,
+}
+
+/// Which entries of a patch-set to keep, to measure what the others save
+pub(crate) enum Subset {
+    /// All but these keys
+    Without(Vec<String>),
+    /// Only these keys
+    Only(Vec<String>),
+    /// None
+    Nothing,
+}
+
+/// The contents of a patch-set file, before resolving what it extends
+struct PatchSetFile {
+    path: PathBuf,
+    extends: Vec<String>,
+    remove: Vec<String>,
+    patches: Table,
+}
+
+impl PatchSet {
+    /// Load `<dir>/<name>.toml`, resolving the patch-sets it extends
+    pub(crate) fn load(dir: &Path, name: &str) -> Result<Self> {
+        resolve(dir, name, &mut Vec::new())
+    }
+
+    /// A patch-set without patches, for zkVMs that need none: applying it only
+    /// removes the patches of the others
+    pub(crate) fn empty(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            path: PathBuf::new(),
+            extends: Vec::new(),
+            patches: Table::new(),
+            provenance: BTreeMap::new(),
+            removed: BTreeMap::new(),
+            variant: None,
+        }
+    }
+
+    /// The patch-set with only the entries `subset` keeps
+    ///
+    /// It is named after its variant, e.g. `succinct-without-sha2`, so that it
+    /// builds in a target directory of its own.
+    pub(crate) fn subset(&self, subset: &Subset) -> Result<Self> {
+        let (variant, keys) = match subset {
+            Subset::Without(keys) => (format!("without-{}", keys.join("-")), keys.as_slice()),
+            Subset::Only(keys) => (format!("only-{}", keys.join("-")), keys.as_slice()),
+            Subset::Nothing => ("no-patches".to_string(), [].as_slice()),
+        };
+        for key in keys {
+            if !self.patches.contains_key(key) {
+                bail!(
+                    "patch-set `{}` has no patch `{key}` (available: {})",
+                    self.name,
+                    self.patches
+                        .iter()
+                        .map(|(key, _)| key)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+        }
+        let keep = |key: &str| match subset {
+            Subset::Without(keys) => !keys.iter().any(|removed| removed == key),
+            Subset::Only(keys) => keys.iter().any(|kept| kept == key),
+            Subset::Nothing => false,
+        };
+
+        let mut patches = self.patches.clone();
+        patches.retain(|key, _| keep(key));
+        let mut provenance = self.provenance.clone();
+        provenance.retain(|key, _| keep(key));
+        Ok(Self {
+            name: format!("{}-{variant}", self.name),
+            path: self.path.clone(),
+            extends: self.extends.clone(),
+            patches,
+            provenance,
+            removed: self.removed.clone(),
+            variant: Some(variant),
+        })
+    }
+
+    /// Load every patch-set in `dir`, sorted by name
+    pub(crate) fn load_all(dir: &Path) -> Result<Vec<Self>> {
+        names(dir)?
+            .iter()
+            .map(|name| Self::load(dir, name))
+            .collect()
+    }
+
+    /// `(key, package)` of every patch, where `package` is the crate being
+    /// patched: the `package` field if there is one, the key otherwise
+    pub(crate) fn crates(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.patches
+            .iter()
+            .map(|(key, item)| (key, package_name(key, item)))
+    }
+}
+
+impl PatchSetFile {
+    fn read(dir: &Path, name: &str) -> Result<Self> {
+        let path = dir.join(format!("{name}.toml"));
+        if !path.exists() {
+            bail!(
+                "unknown patch-set `{name}` (available: {})",
+                names(dir)?.join(", ")
+            );
+        }
+        let doc: DocumentMut = fs::read_to_string(&path)
+            .with_context(|| format!("reading {}", path.display()))?
+            .parse()
+            .with_context(|| format!("parsing {}", path.display()))?;
+
+        let strings = |key: &str| -> Result<Vec<String>> {
+            match doc.get(key) {
+                None => Ok(Vec::new()),
+                Some(item) => match (item.as_str(), item.as_array()) {
+                    (Some(name), _) => Ok(vec![name.to_string()]),
+                    (_, Some(names)) => names
+                        .iter()
+                        .map(|name| name.as_str().map(str::to_string))
+                        .collect::<Option<_>>()
+                        .with_context(|| format!("`{key}` in {} must hold names", path.display())),
+                    _ => bail!("`{key}` in {} must hold names", path.display()),
+                },
+            }
+        };
+        let extends = strings("extends")?;
+        let remove = strings("remove")?;
+        let patches = match patch_table(&doc) {
+            Some(patches) => patches.clone(),
+            None if !extends.is_empty() => Table::new(),
+            None => bail!("{} has no [patch.crates-io] table", path.display()),
+        };
+        Ok(Self {
+            path,
+            extends,
+            remove,
+            patches,
+        })
+    }
+}
+
+/// Resolve patch-set `name`; `stack` holds the patch-sets extending it, to
+/// detect cycles
+fn resolve(dir: &Path, name: &str, stack: &mut Vec<String>) -> Result<PatchSet> {
+    if stack.iter().any(|extending| extending == name) {
+        bail!(
+            "patch-sets extend each other: {} -> {name}",
+            stack.join(" -> ")
+        );
+    }
+    let file = PatchSetFile::read(dir, name)?;
+
+    // 1 ── merge the bases, later ones overriding earlier ones
+    stack.push(name.to_string());
+    let mut patches = Table::new();
+    let mut provenance: BTreeMap<String, Provenance> = BTreeMap::new();
+    let mut removed = BTreeMap::new();
+    for base in &file.extends {
+        let base = resolve(dir, base, stack)?;
+        removed.extend(base.removed.clone());
+        for (key, item) in &base.patches {
+            let mut origin = base.provenance[key].clone();
+            if let Some(previous) = provenance.get(key) {
+                origin.overrides.push(previous.defined_in.clone());
+            }
+            patches[key] = item.clone();
+            provenance.insert(key.to_string(), origin);
+            removed.remove(key);
+        }
+    }
+    stack.pop();
+
+    // 2 ── drop the removed entries
+    for key in &file.remove {
+        if patches.remove(key).is_none() {
+            bail!(
+                "{} removes `{key}`, which none of the patch-sets it extends defines",
+                file.path.display()
+            );
+        }
+        provenance.remove(key);
+        removed.insert(key.clone(), name.to_string());
+    }
+
+    // 3 ── add the patch-set's own entries
+    for (key, item) in &file.patches {
+        let overrides = provenance
+            .get(key)
+            .map(|previous| {
+                std::iter::once(previous.defined_in.clone())
+                    .chain(previous.overrides.iter().cloned())
+                    .collect()
+            })
+            .unwrap_or_default();
+        patches[key] = item.clone();
+        provenance.insert(
+            key.to_string(),
+            Provenance {
+                defined_in: name.to_string(),
+                overrides,
+            },
+        );
+        removed.remove(key);
+    }
+
+    Ok(PatchSet {
+        name: name.to_string(),
+        path: file.path,
+        extends: file.extends,
+        patches,
+        provenance,
+        removed,
+        variant: None,
+    })
+}
+
+/// The crate a `[patch.crates-io]` entry replaces
+pub(crate) fn package_name<'a>(key: &'a str, item: &'a Item) -> &'a str {
+    item.get("package").and_then(Item::as_str).unwrap_or(key)
+}
+
+/// The `[patch.crates-io]` table of a manifest, if it has one
+pub(crate) fn patch_table(doc: &DocumentMut) -> Option<&Table> {
+    doc.get("patch")
+        .and_then(|p| p.get("crates-io"))
+        .and_then(Item::as_table)
+}
+
+/// Every `[patch.crates-io]` entry written in any precompile-patches/*.toml,
+/// as `(key, entry)`
+///
+/// These are the entries xtask may have put in the workspace manifest.
+pub(crate) fn patch_definitions(dir: &Path) -> Result<Vec<(String, Item)>> {
+    let mut definitions = Vec::new();
+    for name in names(dir)? {
+        let file = PatchSetFile::read(dir, &name)?;
+        definitions.extend(
+            file.patches
+                .iter()
+                .map(|(key, item)| (key.to_string(), item.clone())),
+        );
+    }
+    Ok(definitions)
+}
+
+/// Whether two `[patch.crates-io]` entries patch from the same source,
+/// regardless of formatting and field order
+pub(crate) fn same_patch(a: &Item, b: &Item) -> bool {
+    let fields = |item: &Item| {
+        item.as_table_like().map(|table| {
+            table
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string().trim().to_string()))
+                .collect::<BTreeMap<_, _>>()
+        })
+    };
+    fields(a).is_some() && fields(a) == fields(b)
+}
+
+/// Sorted names of the patch-sets in `dir`
+pub(crate) fn names(dir: &Path) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))? {
+        let path = entry?.path();
+        if path.extension().and_then(|s| s.to_str()) == Some("toml") {
+            if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                names.push(stem.to_string());
+            }
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory of patch-sets, from `(name, contents)`
+    fn patch_sets(files: &[(&str, &str)]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, contents) in files {
+            fs::write(dir.path().join(format!("{name}.toml")), contents).unwrap();
+        }
+        dir
+    }
+
+    fn keys(patch_set: &PatchSet) -> Vec<&str> {
+        patch_set.patches.iter().map(|(key, _)| key).collect()
+    }
+
+    const A: &str = r#"
+        [patch.crates-io]
+        sha2 = { git = "https://a/sha2" }
+        k256 = { git = "https://a/k256" }
+    "#;
+    const B: &str = r#"
+        [patch.crates-io]
+        sha2 = { git = "https://b/sha2" }
+    "#;
+
+    #[test]
+    fn later_bases_override_earlier_ones() {
+        let c = r#"
+            extends = ["a", "b"]
+            remove = ["k256"]
+
+            [patch.crates-io]
+            bn = { git = "https://c/bn" }
+        "#;
+        let d = r#"
+            extends = "c"
+
+            [patch.crates-io]
+            sha2 = { git = "https://d/sha2" }
+        "#;
+        let dir = patch_sets(&[("a", A), ("b", B), ("c", c), ("d", d)]);
+
+        let c = PatchSet::load(dir.path(), "c").unwrap();
+        assert_eq!(c.extends, ["a", "b"]);
+        assert_eq!(keys(&c), ["sha2", "bn"]);
+        assert_eq!(c.patches["sha2"]["git"].as_str(), Some("https://b/sha2"));
+        assert_eq!(c.provenance["sha2"].defined_in, "b");
+        assert_eq!(c.provenance["sha2"].overrides, ["a"]);
+        assert_eq!(c.provenance["bn"].defined_in, "c");
+        assert_eq!(c.removed["k256"], "c");
+
+        let d = PatchSet::load(dir.path(), "d").unwrap();
+        assert_eq!(d.patches["sha2"]["git"].as_str(), Some("https://d/sha2"));
+        assert_eq!(d.provenance["sha2"].defined_in, "d");
+        assert_eq!(d.provenance["sha2"].overrides, ["b", "a"]);
+        assert_eq!(d.removed["k256"], "c");
+    }
+
+    #[test]
+    fn only_inherited_entries_can_be_removed() {
+        let c = r#"
+            extends = "a"
+            remove = ["bn"]
+        "#;
+        let dir = patch_sets(&[("a", A), ("c", c)]);
+        let err = PatchSet::load(dir.path(), "c").err().unwrap();
+        assert!(
+            err.to_string()
+                .ends_with("removes `bn`, which none of the patch-sets it extends defines"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        let dir = patch_sets(&[("x", r#"extends = "y""#), ("y", r#"extends = "x""#)]);
+        let err = PatchSet::load(dir.path(), "x").err().unwrap();
+        assert_eq!(err.to_string(), "patch-sets extend each other: x -> y -> x");
+    }
+
+    #[test]
+    fn patch_sets_need_patches_or_bases() {
+        let dir = patch_sets(&[("a", A), ("empty", "")]);
+        assert!(PatchSet::load(dir.path(), "empty").is_err());
+        let err = PatchSet::load(dir.path(), "missing").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "unknown patch-set `missing` (available: a, empty)"
+        );
+    }
+
+    #[test]
+    fn patches_are_compared_regardless_of_formatting() {
+        let doc: DocumentMut = r#"
+            a = { git = "https://a/sha2", tag = "v1" }
+            b = {tag="v1",git="https://a/sha2"}
+            c = { git = "https://a/sha2", tag = "v2" }
+            d = "0.10"
+            e = "0.10"
+        "#
+        .parse()
+        .unwrap();
+        assert!(same_patch(&doc["a"], &doc["b"]));
+        assert!(!same_patch(&doc["a"], &doc["c"]));
+        assert!(!same_patch(&doc["d"], &doc["e"]));
+    }
+
+    #[test]
+    fn subsets_are_named_after_their_variant() {
+        let dir = patch_sets(&[("a", A)]);
+        let a = PatchSet::load(dir.path(), "a").unwrap();
+
+        let without = a.subset(&Subset::Without(vec!["sha2".into()])).unwrap();
+        assert_eq!(without.name, "a-without-sha2");
+        assert_eq!(without.variant.as_deref(), Some("without-sha2"));
+        assert_eq!(keys(&without), ["k256"]);
+        assert!(without.provenance.keys().eq(["k256"]));
+
+        let only = a
+            .subset(&Subset::Only(vec!["sha2".into(), "k256".into()]))
+            .unwrap();
+        assert_eq!(only.name, "a-only-sha2-k256");
+        assert_eq!(keys(&only), ["sha2", "k256"]);
+
+        let nothing = a.subset(&Subset::Nothing).unwrap();
+        assert_eq!(nothing.name, "a-no-patches");
+        assert!(nothing.patches.is_empty());
+    }
+
+    #[test]
+    fn subsets_name_existing_patches() {
+        let dir = patch_sets(&[("a", A)]);
+        let a = PatchSet::load(dir.path(), "a").unwrap();
+        let err = a.subset(&Subset::Only(vec!["bn".into()])).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "patch-set `a` has no patch `bn` (available: sha2, k256)"
+        );
+    }
+}
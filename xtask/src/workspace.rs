@@ -0,0 +1,329 @@
+//! Applying a patch-set to the workspace for the duration of a Cargo command
+//!
+//! The workspace `Cargo.toml` is only patched while Cargo runs: its original
+//! contents are restored afterwards, whether Cargo succeeds, fails or is
+//! interrupted with Ctrl-C.
+//!
+//! Each patch-set builds into its own target directory, `target/<patch-set>`,
+//! with its own `Cargo.lock` kept there, so switching patch-sets does not
+//! invalidate the build cache of the others. Cargo is given the directory in
+//! `CARGO_TARGET_DIR` and `ZKEVM_TARGET_DIR`; hosts locate their guest ELFs
+//! through the latter. It is also given the keys of the applied patches in
+//! `ZKEVM_PATCHES` and, for a subset of a patch-set, its variant in
+//! `ZKEVM_RUN_VARIANT`, which the benchmark runner records with the results.
+
+use anyhow::{Context, Result};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use toml_edit::{DocumentMut, Item, Table};
+
+use crate::patch_set::{PatchSet, patch_definitions, same_patch};
+
+/// The workspace with a patch-set applied, restored when dropped
+pub(crate) struct PatchedWorkspace {
+    target_dir: PathBuf,
+    lockfile_path: PathBuf,
+    /// Keys of the applied patches
+    patches: Vec<String>,
+    variant: Option<String>,
+    // Dropped after `Drop::drop` saved the lockfile, which restores both files
+    _manifest: FileGuard,
+    _lockfile: FileGuard,
+}
+
+impl PatchedWorkspace {
+    /// Inject `patch_set` into the workspace `Cargo.toml`, replacing previous
+    /// precompile patches, and swap in the patch-set's lockfile
+    pub(crate) fn apply(ws_root: &Path, patch_set: &PatchSet) -> Result<Self> {
+        let manifest_path = ws_root.join("Cargo.toml");
+        let backup_dir = ws_root.join("target/xtask");
+
+        // 1 ── read root manifest, undoing a previous run that could not clean up
+        let manifest = FileGuard::new(&manifest_path, &backup_dir.join("Cargo.toml.orig"))?;
+        let mut root_doc: DocumentMut = manifest
+            .original
+            .as_deref()
+            .with_context(|| format!("{} does not exist", manifest_path.display()))?
+            .parse()?;
+
+        // 2 ── remove the patches a previous run left behind, ie the entries
+        //      identical to one of a patch-set; the workspace's own patches stay
+        let definitions = patch_definitions(&ws_root.join("precompile-patches"))?;
+        let ci = root_doc["patch"]["crates-io"]
+            .or_insert(Item::Table(Table::new()))
+            .as_table_mut()
+            .context("[patch.crates-io] of the workspace manifest is not a table")?;
+        ci.retain(|key, item| {
+            !definitions
+                .iter()
+                .any(|(owned, definition)| owned == key && same_patch(definition, item))
+        });
+
+        // 3 ── insert the selected patch-set
+        for (k, v) in &patch_set.patches {
+            if ci.contains_key(k) {
+                eprintln!(
+                    "patch-set `{}` overrides the workspace's own patch of `{k}`",
+                    patch_set.name
+                );
+            }
+            ci[k] = v.clone(); // overwrite / insert
+        }
+
+        // 4 ── write the updated manifest and the patch-set's lockfile, both
+        //      restored when their guards are dropped
+        let target_dir = match env::var_os("CARGO_TARGET_DIR") {
+            Some(dir) => PathBuf::from(dir),
+            None => patch_set_target_dir(ws_root, &patch_set.name),
+        };
+        let lockfile_path = ws_root.join("Cargo.lock");
+        let lockfile = FileGuard::new(&lockfile_path, &backup_dir.join("Cargo.lock.orig"))?;
+        manifest.write(&root_doc.to_string())?;
+        if let Ok(lock) = fs::read_to_string(target_dir.join("Cargo.lock")) {
+            lockfile.write(&lock)?;
+        }
+
+        Ok(Self {
+            target_dir,
+            lockfile_path,
+            patches: patch_set
+                .patches
+                .iter()
+                .map(|(key, _)| key.to_string())
+                .collect(),
+            variant: patch_set.variant.clone(),
+            _manifest: manifest,
+            _lockfile: lockfile,
+        })
+    }
+
+    /// A `cargo` command building into the patch-set's target directory
+    pub(crate) fn cargo(&self) -> Command {
+        let mut cargo = Command::new("cargo");
+        cargo
+            .env("CARGO_TARGET_DIR", &self.target_dir)
+            .env("ZKEVM_TARGET_DIR", &self.target_dir)
+            .env("ZKEVM_PATCHES", self.patches.join(","));
+        match &self.variant {
+            Some(variant) => cargo.env("ZKEVM_RUN_VARIANT", variant),
+            None => cargo.env_remove("ZKEVM_RUN_VARIANT"),
+        };
+        cargo
+    }
+
+    /// Target directory of the patch-set, where Cargo builds
+    pub(crate) fn target_dir(&self) -> &Path {
+        &self.target_dir
+    }
+
+    /// The workspace lockfile, as resolved with the patch-set
+    pub(crate) fn lockfile(&self) -> &Path {
+        &self.lockfile_path
+    }
+
+    /// Keep the lockfile Cargo resolved for the next run of this patch-set
+    fn save_lockfile(&self) -> Result<()> {
+        let Ok(lock) = fs::read_to_string(&self.lockfile_path) else {
+            return Ok(());
+        };
+        let path = self.target_dir.join("Cargo.lock");
+        fs::create_dir_all(&self.target_dir)?;
+        fs::write(&path, lock).with_context(|| format!("writing {}", path.display()))
+    }
+}
+
+impl Drop for PatchedWorkspace {
+    fn drop(&mut self) {
+        if let Err(err) = self.save_lockfile() {
+            eprintln!("failed to save the lockfile of the patch-set: {err:#}");
+        }
+    }
+}
+
+/// Target directory of a patch-set, where Cargo builds with it and keeps its
+/// lockfile.
+pub(crate) fn patch_set_target_dir(ws_root: &Path, patch: &str) -> PathBuf {
+    ws_root.join("target").join(patch)
+}
+
+/// Keeps the original contents of a workspace file (the manifest or the
+/// lockfile) while xtask replaces it, and writes them back when dropped.
+///
+/// The original is also saved to a backup file for as long as the guard lives,
+/// so that it can be recovered if xtask is killed without a chance to clean
+/// up: the next run restores it before doing anything else.
+struct FileGuard {
+    path: PathBuf,
+    backup: PathBuf,
+    /// Original contents, or `None` if the file did not exist.
+    original: Option<String>,
+}
+
+impl FileGuard {
+    fn new(path: &Path, backup: &Path) -> Result<Self> {
+        if backup.exists() {
+            eprintln!(
+                "restoring {} from {}, left by an interrupted run",
+                path.display(),
+                backup.display()
+            );
+            fs::copy(backup, path).with_context(|| format!("restoring {}", path.display()))?;
+            fs::remove_file(backup)?;
+        }
+        let original = path
+            .exists()
+            .then(|| fs::read_to_string(path))
+            .transpose()
+            .with_context(|| format!("reading {}", path.display()))?;
+        if let Some(original) = &original {
+            if let Some(parent) = backup.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(backup, original).with_context(|| format!("writing {}", backup.display()))?;
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            backup: backup.to_path_buf(),
+            original,
+        })
+    }
+
+    /// Replaces the file with `contents`, until the guard is dropped.
+    fn write(&self, contents: &str) -> Result<()> {
+        fs::write(&self.path, contents).with_context(|| format!("writing {}", self.path.display()))
+    }
+}
+
+impl Drop for FileGuard {
+    fn drop(&mut self) {
+        let restored = match &self.original {
+            Some(original) => fs::write(&self.path, original),
+            None => fs::remove_file(&self.path).or_else(|err| match err.kind() {
+                io::ErrorKind::NotFound => Ok(()),
+                _ => Err(err),
+            }),
+        };
+        match restored {
+            Ok(()) => {
+                let _ = fs::remove_file(&self.backup);
+            }
+            Err(err) => eprintln!(
+                "failed to restore {} ({err}), its original is kept in {}",
+                self.path.display(),
+                self.backup.display()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guarded_files_are_restored() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Cargo.toml");
+        let backup = dir.path().join("target/xtask/Cargo.toml.orig");
+        fs::write(&path, "original").unwrap();
+
+        let guard = FileGuard::new(&path, &backup).unwrap();
+        guard.write("patched").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "patched");
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "original");
+        drop(guard);
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "original");
+        assert!(!backup.exists());
+    }
+
+    #[test]
+    fn guarded_files_that_did_not_exist_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Cargo.lock");
+        let backup = dir.path().join("target/xtask/Cargo.lock.orig");
+
+        let guard = FileGuard::new(&path, &backup).unwrap();
+        assert!(!backup.exists());
+        guard.write("resolved").unwrap();
+        drop(guard);
+
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn interrupted_runs_are_undone() {
+        let ws = tempfile::tempdir().unwrap();
+        let ws_root = ws.path();
+        let manifest = "[workspace]\nmembers = []\n";
+        fs::create_dir_all(ws_root.join("precompile-patches")).unwrap();
+        fs::create_dir_all(ws_root.join("target/xtask")).unwrap();
+        fs::write(ws_root.join("target/xtask/Cargo.toml.orig"), manifest).unwrap();
+        fs::write(ws_root.join("Cargo.toml"), "left patched").unwrap();
+
+        let workspace = PatchedWorkspace::apply(ws_root, &PatchSet::empty("none")).unwrap();
+        let patched: DocumentMut = fs::read_to_string(ws_root.join("Cargo.toml"))
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(patched["workspace"]["members"].is_array());
+        drop(workspace);
+
+        assert_eq!(
+            fs::read_to_string(ws_root.join("Cargo.toml")).unwrap(),
+            manifest
+        );
+        assert!(!ws_root.join("target/xtask/Cargo.toml.orig").exists());
+    }
+
+    #[test]
+    fn patch_sets_replace_the_patches_of_others() {
+        let ws = tempfile::tempdir().unwrap();
+        let ws_root = ws.path();
+        let manifest = r#"[workspace]
+members = []
+
+[patch.crates-io]
+own = { path = "own" }
+sha2 = { git = "https://stale/sha2" }
+"#;
+        fs::write(ws_root.join("Cargo.toml"), manifest).unwrap();
+        fs::create_dir_all(ws_root.join("precompile-patches")).unwrap();
+        fs::write(
+            ws_root.join("precompile-patches/stale.toml"),
+            "[patch.crates-io]\nsha2 = { git = \"https://stale/sha2\" }\n",
+        )
+        .unwrap();
+        fs::write(
+            ws_root.join("precompile-patches/fresh.toml"),
+            "[patch.crates-io]\nk256 = { git = \"https://fresh/k256\" }\n",
+        )
+        .unwrap();
+
+        let fresh = PatchSet::load(&ws_root.join("precompile-patches"), "fresh").unwrap();
+        let workspace = PatchedWorkspace::apply(ws_root, &fresh).unwrap();
+        let patched: DocumentMut = fs::read_to_string(ws_root.join("Cargo.toml"))
+            .unwrap()
+            .parse()
+            .unwrap();
+        let patches: Vec<_> = patched["patch"]["crates-io"]
+            .as_table()
+            .unwrap()
+            .iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(patches, ["own", "k256"]);
+        let cargo = workspace.cargo();
+        let mut envs = cargo.get_envs();
+        assert!(envs.any(|env| env == ("ZKEVM_PATCHES".as_ref(), Some("k256".as_ref()))));
+        drop(workspace);
+
+        assert_eq!(
+            fs::read_to_string(ws_root.join("Cargo.toml")).unwrap(),
+            manifest
+        );
+    }
+}